@@ -0,0 +1,725 @@
+/*
+    Assembling source into a ROM.
+
+    The syntax uses Cowgod's mnemonics, the same ones `disasm` prints. There is
+    one statement per line and `;` starts a comment:
+
+        SPEED equ 2             ; a constant
+        main:                   ; a label
+            LD V0, SPEED
+            CALL draw
+            JP main
+        draw:
+            LD I, sprite
+            DRW V0, V1, 3
+            RET
+        sprite:
+            db 0b11100000, 0xA0, 0xE0
+            dw 0x1234
+
+    `include "file.asm"` pulls in another file, found relative to the one that
+    includes it. Macros are defined with `macro name arg, ...` up to `endm`, and
+    used like an instruction. Their arguments are substituted into the body as
+    text.
+
+    Numbers are decimal, 0x hex or 0b binary. Anywhere a number is expected,
+    labels and constants can be used, added together or subtracted. `LD I, long
+    addr` is XO-CHIP's four byte F000 load.
+
+    Along with the bytes, assembling produces a `SourceMap` from the address of
+    each statement to the line it came from, which the debugger uses to show
+    source lines while stepping.
+*/
+
+use crate::instruction::Instruction;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const PROGRAM_START: usize = 0x200;
+const MAX_NESTING: usize = 16;
+
+/// A line in a source file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Location {
+    pub file: PathBuf,
+    pub line: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.file.display(), self.line)
+    }
+}
+
+#[derive(Debug)]
+pub struct AsmError {
+    /// Where the problem is, when it can be pinned to a line.
+    pub location: Option<Location>,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.location {
+            Some(location) => write!(f, "{}: {}", location, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl Error for AsmError {}
+
+fn error_at(location: &Location, message: String) -> AsmError {
+    AsmError {
+        location: Some(location.clone()),
+        message,
+    }
+}
+
+/// Which source line each address was assembled from.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    locations: BTreeMap<usize, Location>,
+    // The text of each source file, when it is available
+    sources: HashMap<PathBuf, Vec<String>>,
+}
+
+impl SourceMap {
+    pub fn insert(&mut self, address: usize, location: Location) {
+        self.locations.insert(address, location);
+    }
+
+    /// The line a statement starting at `address` came from.
+    pub fn lookup(&self, address: usize) -> Option<&Location> {
+        self.locations.get(&address)
+    }
+
+    /// The location of `address` followed by the text of the line, if the
+    /// source file could be read.
+    pub fn source_line(&self, address: usize) -> Option<String> {
+        let location = self.lookup(address)?;
+        let text = self
+            .sources
+            .get(&location.file)
+            .and_then(|lines| lines.get(location.line - 1));
+        Some(match text {
+            Some(text) => format!("{}: {}", location, text.trim()),
+            None => location.to_string(),
+        })
+    }
+
+    /// Reads a map written by `to_text`, along with the source files it refers
+    /// to. Relative paths are tried from the working directory, then from the
+    /// map's own directory.
+    pub fn load(path: &Path) -> Result<SourceMap, String> {
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let mut map = SourceMap::parse(&text)?;
+        let directory = path.parent().unwrap_or(Path::new(""));
+        let files: Vec<PathBuf> = map.locations.values().map(|l| l.file.clone()).collect();
+        for file in files {
+            if map.sources.contains_key(&file) {
+                continue;
+            }
+            let source =
+                fs::read_to_string(&file).or_else(|_| fs::read_to_string(directory.join(&file)));
+            if let Ok(source) = source {
+                map.sources
+                    .insert(file, source.lines().map(String::from).collect());
+            }
+        }
+        Ok(map)
+    }
+
+    /// Parses the text written by `to_text`.
+    pub fn parse(text: &str) -> Result<SourceMap, String> {
+        let mut map = SourceMap::default();
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let parsed = line.trim().split_once(' ').and_then(|(address, location)| {
+                let (file, number) = location.rsplit_once(':')?;
+                let address = usize::from_str_radix(address.strip_prefix("0x")?, 16).ok()?;
+                let number = number.parse().ok().filter(|&number| number > 0)?;
+                Some((address, file, number))
+            });
+            let Some((address, file, line)) = parsed else {
+                return Err(format!("Invalid source map line '{}'", line));
+            };
+            map.insert(
+                address,
+                Location {
+                    file: PathBuf::from(file),
+                    line,
+                },
+            );
+        }
+        Ok(map)
+    }
+
+    /// One `address file:line` entry per line.
+    pub fn to_text(&self) -> String {
+        self.locations
+            .iter()
+            .map(|(address, location)| format!("{:#06X} {}\n", address, location))
+            .collect()
+    }
+}
+
+pub struct Assembly {
+    pub rom: Vec<u8>,
+    pub source_map: SourceMap,
+}
+
+/// Assembles the file at `path` and everything it includes.
+pub fn assemble_file(path: &Path) -> Result<Assembly, AsmError> {
+    let source = fs::read_to_string(path).map_err(|e| AsmError {
+        location: None,
+        message: format!("Unable to read {}: {}", path.display(), e),
+    })?;
+    assemble(&source, path)
+}
+
+/// Assembles `source`. `path` is where it came from, used for the source map
+/// and to find included files.
+pub fn assemble(source: &str, path: &Path) -> Result<Assembly, AsmError> {
+    let mut preprocessor = Preprocessor::default();
+    preprocessor.read(source, path, 0)?;
+
+    // First pass: work out where everything goes
+    let mut symbols = HashMap::new();
+    let mut statements = Vec::new();
+    let mut address = PROGRAM_START;
+    for line in &preprocessor.lines {
+        let mut text = line.text.as_str();
+        if let Some((label, rest)) = split_label(text) {
+            define(
+                &mut symbols,
+                label,
+                Symbol::Address(address),
+                &line.location,
+            )?;
+            text = rest;
+        }
+        if text.is_empty() {
+            continue;
+        }
+        let (word, rest) = split_word(text);
+        if let Some((name, value)) = rest
+            .strip_prefix("equ ")
+            .or_else(|| rest.strip_prefix("EQU "))
+            .map(|value| (word, value))
+        {
+            let constant = Symbol::Constant(value.trim().to_string());
+            define(&mut symbols, name, constant, &line.location)?;
+            continue;
+        }
+        let statement = Statement::parse(word, rest);
+        let size = statement.size();
+        statements.push((address, statement, &line.location));
+        address += size;
+    }
+
+    // Second pass: encode it
+    let evaluator = Evaluator { symbols: &symbols };
+    let mut rom = Vec::new();
+    let mut source_map = SourceMap {
+        locations: BTreeMap::new(),
+        sources: preprocessor.sources,
+    };
+    for (address, statement, location) in statements {
+        let bytes = statement
+            .encode(&evaluator)
+            .map_err(|message| error_at(location, message))?;
+        debug_assert_eq!(rom.len(), address - PROGRAM_START);
+        rom.extend_from_slice(&bytes);
+        source_map.insert(address, location.clone());
+    }
+    Ok(Assembly { rom, source_map })
+}
+
+// A statement with its includes and macros expanded and its comment removed
+struct Line {
+    text: String,
+    location: Location,
+}
+
+struct Macro {
+    params: Vec<String>,
+    body: Vec<(String, Location)>,
+}
+
+#[derive(Default)]
+struct Preprocessor {
+    lines: Vec<Line>,
+    macros: HashMap<String, Macro>,
+    sources: HashMap<PathBuf, Vec<String>>,
+}
+
+impl Preprocessor {
+    fn read(&mut self, source: &str, file: &Path, depth: usize) -> Result<(), AsmError> {
+        self.sources.insert(
+            file.to_path_buf(),
+            source.lines().map(String::from).collect(),
+        );
+        let mut lines = source.lines().enumerate().map(|(i, text)| {
+            let location = Location {
+                file: file.to_path_buf(),
+                line: i + 1,
+            };
+            (strip_comment(text).trim().to_string(), location)
+        });
+        while let Some((text, location)) = lines.next() {
+            let (word, rest) = split_word(&text);
+            match word.to_ascii_lowercase().as_str() {
+                "include" => {
+                    let name = rest.trim().trim_matches('"');
+                    let path = file.parent().unwrap_or(Path::new("")).join(name);
+                    if depth >= MAX_NESTING {
+                        return Err(error_at(&location, "Includes are nested too deeply".into()));
+                    }
+                    let source = fs::read_to_string(&path).map_err(|e| {
+                        error_at(
+                            &location,
+                            format!("Unable to include {}: {}", path.display(), e),
+                        )
+                    })?;
+                    self.read(&source, &path, depth + 1)?;
+                }
+                "macro" => {
+                    let (name, params) = split_word(rest);
+                    if name.is_empty() {
+                        return Err(error_at(&location, "A macro needs a name".into()));
+                    }
+                    let mut body = Vec::new();
+                    loop {
+                        match lines.next() {
+                            Some((text, _)) if text.eq_ignore_ascii_case("endm") => break,
+                            Some(line) => body.push(line),
+                            None => {
+                                return Err(error_at(
+                                    &location,
+                                    format!("Macro {} has no endm", name),
+                                ))
+                            }
+                        }
+                    }
+                    let params = split_operands(params)
+                        .into_iter()
+                        .map(String::from)
+                        .collect();
+                    self.macros.insert(name.to_string(), Macro { params, body });
+                }
+                "endm" => return Err(error_at(&location, "endm without a macro".into())),
+                _ => self.statement(&text, location, 0)?,
+            }
+        }
+        Ok(())
+    }
+
+    // Adds a statement, expanding it if it uses a macro. Macro expansions keep
+    // the location of the line that used the macro.
+    fn statement(&mut self, text: &str, location: Location, depth: usize) -> Result<(), AsmError> {
+        let mut text = text;
+        if let Some((label, rest)) = split_label(text) {
+            self.lines.push(Line {
+                text: format!("{}:", label),
+                location: location.clone(),
+            });
+            text = rest;
+        }
+        let (word, rest) = split_word(text);
+        let Some(definition) = self.macros.get(word) else {
+            if !text.is_empty() {
+                self.lines.push(Line {
+                    text: text.to_string(),
+                    location,
+                });
+            }
+            return Ok(());
+        };
+
+        if depth >= MAX_NESTING {
+            return Err(error_at(&location, "Macros are nested too deeply".into()));
+        }
+        let args = split_operands(rest);
+        if args.len() != definition.params.len() {
+            return Err(error_at(
+                &location,
+                format!(
+                    "Macro {} takes {} arguments, got {}",
+                    word,
+                    definition.params.len(),
+                    args.len()
+                ),
+            ));
+        }
+        let body: Vec<String> = definition
+            .body
+            .iter()
+            .map(|(line, _)| substitute(line, &definition.params, &args))
+            .collect();
+        for line in body {
+            self.statement(&line, location.clone(), depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+// Replaces each whole word in `params` with the matching argument
+fn substitute(text: &str, params: &[String], args: &[&str]) -> String {
+    let mut out = String::new();
+    let mut word = String::new();
+    for c in text.chars().chain(std::iter::once('\n')) {
+        if is_symbol_char(c) {
+            word.push(c);
+            continue;
+        }
+        match params.iter().position(|param| *param == word) {
+            Some(i) => out.push_str(args[i]),
+            None => out.push_str(&word),
+        }
+        word.clear();
+        if c != '\n' {
+            out.push(c);
+        }
+    }
+    out
+}
+
+enum Symbol {
+    Address(usize),
+    Constant(String),
+}
+
+fn define(
+    symbols: &mut HashMap<String, Symbol>,
+    name: &str,
+    symbol: Symbol,
+    location: &Location,
+) -> Result<(), AsmError> {
+    if !is_symbol(name) {
+        return Err(error_at(location, format!("Invalid name '{}'", name)));
+    }
+    if symbols.insert(name.to_string(), symbol).is_some() {
+        return Err(error_at(location, format!("{} is already defined", name)));
+    }
+    Ok(())
+}
+
+struct Evaluator<'a> {
+    symbols: &'a HashMap<String, Symbol>,
+}
+
+impl Evaluator<'_> {
+    // Sums the terms of an expression such as `table + 2 - OFFSET`
+    fn evaluate(&self, expression: &str) -> Result<i64, String> {
+        self.evaluate_nested(expression, 0)
+    }
+
+    fn evaluate_nested(&self, expression: &str, depth: usize) -> Result<i64, String> {
+        if depth > MAX_NESTING {
+            return Err(format!("'{}' refers back to itself", expression.trim()));
+        }
+        let expression = expression.trim();
+        if expression.is_empty() {
+            return Err("Expected a value".to_string());
+        }
+        let mut total: i64 = 0;
+        let mut sign = 1;
+        let mut term = String::new();
+        for c in expression.chars().chain(std::iter::once('+')) {
+            if c != '+' && c != '-' {
+                term.push(c);
+                continue;
+            }
+            let trimmed = term.trim();
+            if trimmed.is_empty() {
+                // A sign with no term before it, as in -1
+                if c == '-' {
+                    sign = -sign;
+                }
+                continue;
+            }
+            total = self
+                .term(trimmed, depth)?
+                .checked_mul(sign)
+                .and_then(|value| total.checked_add(value))
+                .ok_or_else(|| format!("'{}' is too large", expression))?;
+            sign = if c == '-' { -1 } else { 1 };
+            term.clear();
+        }
+        Ok(total)
+    }
+
+    fn term(&self, term: &str, depth: usize) -> Result<i64, String> {
+        if let Some(number) = parse_number(term) {
+            return Ok(number);
+        }
+        match self.symbols.get(term) {
+            Some(Symbol::Address(address)) => Ok(*address as i64),
+            Some(Symbol::Constant(value)) => self.evaluate_nested(value, depth + 1),
+            None => Err(format!("Unknown name '{}'", term)),
+        }
+    }
+
+    fn value(&self, expression: &str, min: i64, max: i64) -> Result<i64, String> {
+        let value = self.evaluate(expression)?;
+        if value < min || value > max {
+            return Err(format!(
+                "{} is out of range, expected {} to {:#X}",
+                expression.trim(),
+                min,
+                max
+            ));
+        }
+        Ok(value)
+    }
+
+    fn address(&self, expression: &str) -> Result<usize, String> {
+        Ok(self.value(expression, 0, 0xFFF)? as usize)
+    }
+
+    // Negative bytes are stored as two's complement, so ADD V0, -1 works
+    fn byte(&self, expression: &str) -> Result<u8, String> {
+        Ok(self.value(expression, -128, 0xFF)? as u8)
+    }
+
+    fn nibble(&self, expression: &str) -> Result<usize, String> {
+        Ok(self.value(expression, 0, 0xF)? as usize)
+    }
+}
+
+enum Statement {
+    Data {
+        width: usize,
+        values: Vec<String>,
+    },
+    Instruction {
+        mnemonic: String,
+        operands: Vec<String>,
+    },
+}
+
+impl Statement {
+    fn parse(word: &str, rest: &str) -> Statement {
+        let operands = split_operands(rest).into_iter().map(String::from).collect();
+        match word.to_ascii_lowercase().as_str() {
+            "db" => Statement::Data {
+                width: 1,
+                values: operands,
+            },
+            "dw" => Statement::Data {
+                width: 2,
+                values: operands,
+            },
+            _ => Statement::Instruction {
+                mnemonic: word.to_ascii_uppercase(),
+                operands,
+            },
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            Statement::Data { width, values } => width * values.len(),
+            Statement::Instruction { mnemonic, operands } => {
+                let is_long = mnemonic == "LD"
+                    && operands.len() == 2
+                    && operands[0].eq_ignore_ascii_case("I")
+                    && long_operand(&operands[1]).is_some();
+                if is_long {
+                    4
+                } else {
+                    2
+                }
+            }
+        }
+    }
+
+    fn encode(&self, evaluator: &Evaluator) -> Result<Vec<u8>, String> {
+        match self {
+            Statement::Data { width: 1, values } => {
+                values.iter().map(|value| evaluator.byte(value)).collect()
+            }
+            Statement::Data { values, .. } => {
+                let mut bytes = Vec::new();
+                for value in values {
+                    let word = evaluator.value(value, -0x8000, 0xFFFF)? as u16;
+                    bytes.extend_from_slice(&word.to_be_bytes());
+                }
+                Ok(bytes)
+            }
+            Statement::Instruction { mnemonic, operands } => {
+                let operands: Vec<&str> = operands.iter().map(String::as_str).collect();
+                let instruction = encode_instruction(mnemonic, &operands, evaluator)?;
+                let mut bytes = instruction.encode().to_be_bytes().to_vec();
+                if instruction == Instruction::LdILong {
+                    let address = evaluator.value(long_operand(operands[1]).unwrap(), 0, 0xFFFF)?;
+                    bytes.extend_from_slice(&(address as u16).to_be_bytes());
+                }
+                Ok(bytes)
+            }
+        }
+    }
+}
+
+// Works out the instruction from its mnemonic and the shape of its operands
+fn encode_instruction(
+    mnemonic: &str,
+    operands: &[&str],
+    evaluator: &Evaluator,
+) -> Result<Instruction, String> {
+    let reg = |i: usize| register(operands[i]);
+    let is = |i: usize, name: &str| operands[i].eq_ignore_ascii_case(name);
+    let vx = || reg(0).ok_or_else(|| format!("Expected a register, got '{}'", operands[0]));
+    let vy = || reg(1).ok_or_else(|| format!("Expected a register, got '{}'", operands[1]));
+
+    let instruction = match (mnemonic, operands.len()) {
+        ("CLS", 0) => Instruction::Cls,
+        ("RET", 0) => Instruction::Ret,
+        ("SCR", 0) => Instruction::Scr,
+        ("SCL", 0) => Instruction::Scl,
+        ("EXIT", 0) => Instruction::Exit,
+        ("LOW", 0) => Instruction::Low,
+        ("HIGH", 0) => Instruction::High,
+        ("AUDIO", 0) => Instruction::Audio,
+        ("SCD", 1) => Instruction::Scd(evaluator.nibble(operands[0])?),
+        ("SCU", 1) => Instruction::Scu(evaluator.nibble(operands[0])?),
+        ("PLANE", 1) => Instruction::Plane(evaluator.value(operands[0], 0, 3)? as usize),
+        ("SYS", 1) => Instruction::Sys(evaluator.address(operands[0])?),
+        ("JP", 1) => Instruction::Jp(evaluator.address(operands[0])?),
+        ("JP", 2) if is(0, "V0") => {
+            let nnn = evaluator.address(operands[1])?;
+            Instruction::JpV0(nnn >> 8, nnn)
+        }
+        ("CALL", 1) => Instruction::Call(evaluator.address(operands[0])?),
+        ("SE", 2) => match reg(1) {
+            Some(y) => Instruction::SeReg(vx()?, y),
+            None => Instruction::SeByte(vx()?, evaluator.byte(operands[1])?),
+        },
+        ("SNE", 2) => match reg(1) {
+            Some(y) => Instruction::SneReg(vx()?, y),
+            None => Instruction::SneByte(vx()?, evaluator.byte(operands[1])?),
+        },
+        ("SAVE" | "LOAD", 1) => {
+            let (x, y) = operands[0]
+                .split_once('-')
+                .and_then(|(x, y)| Some((register(x.trim())?, register(y.trim())?)))
+                .ok_or_else(|| format!("Expected a range like V0 - V3, got '{}'", operands[0]))?;
+            if mnemonic == "SAVE" {
+                Instruction::Save(x, y)
+            } else {
+                Instruction::Load(x, y)
+            }
+        }
+        ("LD", 2) => match (reg(0), reg(1)) {
+            (Some(x), Some(y)) => Instruction::LdReg(x, y),
+            (Some(x), None) if is(1, "DT") => Instruction::LdVxDt(x),
+            (Some(x), None) if is(1, "K") => Instruction::LdVxK(x),
+            (Some(x), None) if is(1, "[I]") => Instruction::LdVxI(x),
+            (Some(x), None) if is(1, "R") => Instruction::LdVxR(x),
+            (Some(x), None) => Instruction::LdByte(x, evaluator.byte(operands[1])?),
+            (None, Some(x)) if is(0, "DT") => Instruction::LdDtVx(x),
+            (None, Some(x)) if is(0, "ST") => Instruction::LdStVx(x),
+            (None, Some(x)) if is(0, "F") => Instruction::LdF(x),
+            (None, Some(x)) if is(0, "HF") => Instruction::LdHf(x),
+            (None, Some(x)) if is(0, "B") => Instruction::LdB(x),
+            (None, Some(x)) if is(0, "[I]") => Instruction::LdIVx(x),
+            (None, Some(x)) if is(0, "R") => Instruction::LdRVx(x),
+            (None, _) if is(0, "I") && long_operand(operands[1]).is_some() => Instruction::LdILong,
+            (None, _) if is(0, "I") => Instruction::LdI(evaluator.address(operands[1])?),
+            _ => return Err(format!("Unknown form of LD: {}", operands.join(", "))),
+        },
+        ("ADD", 2) => match (reg(0), reg(1)) {
+            (Some(x), Some(y)) => Instruction::AddReg(x, y),
+            (Some(x), None) => Instruction::AddByte(x, evaluator.byte(operands[1])?),
+            (None, Some(x)) if is(0, "I") => Instruction::AddIVx(x),
+            _ => return Err(format!("Unknown form of ADD: {}", operands.join(", "))),
+        },
+        ("OR", 2) => Instruction::Or(vx()?, vy()?),
+        ("AND", 2) => Instruction::And(vx()?, vy()?),
+        ("XOR", 2) => Instruction::Xor(vx()?, vy()?),
+        ("SUB", 2) => Instruction::Sub(vx()?, vy()?),
+        ("SUBN", 2) => Instruction::Subn(vx()?, vy()?),
+        // With only one register, shift it in place whichever quirk is in use
+        ("SHR", 1) => Instruction::Shr(vx()?, vx()?),
+        ("SHR", 2) => Instruction::Shr(vx()?, vy()?),
+        ("SHL", 1) => Instruction::Shl(vx()?, vx()?),
+        ("SHL", 2) => Instruction::Shl(vx()?, vy()?),
+        ("RND", 2) => Instruction::Rnd(vx()?, evaluator.byte(operands[1])?),
+        ("DRW", 3) => Instruction::Drw(vx()?, vy()?, evaluator.nibble(operands[2])?),
+        ("SKP", 1) => Instruction::Skp(vx()?),
+        ("SKNP", 1) => Instruction::Sknp(vx()?),
+        ("PITCH", 1) => Instruction::Pitch(vx()?),
+        _ => {
+            return Err(format!(
+                "Unknown instruction '{}' with {} operands",
+                mnemonic,
+                operands.len()
+            ))
+        }
+    };
+    Ok(instruction)
+}
+
+// The address in `long addr`
+fn long_operand(operand: &str) -> Option<&str> {
+    let (word, rest) = split_word(operand);
+    word.eq_ignore_ascii_case("long").then_some(rest)
+}
+
+fn register(operand: &str) -> Option<usize> {
+    let (v, x) = operand.split_at_checked(1)?;
+    if !v.eq_ignore_ascii_case("v") || x.len() != 1 {
+        return None;
+    }
+    usize::from_str_radix(x, 16).ok()
+}
+
+fn parse_number(text: &str) -> Option<i64> {
+    let lower = text.to_ascii_lowercase();
+    if let Some(hex) = lower.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()
+    } else if let Some(binary) = lower.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2).ok()
+    } else {
+        lower.parse().ok()
+    }
+}
+
+fn strip_comment(line: &str) -> &str {
+    line.split(';').next().unwrap_or("")
+}
+
+// Splits `label: rest` at the colon
+fn split_label(text: &str) -> Option<(&str, &str)> {
+    let (label, rest) = text.split_once(':')?;
+    is_symbol(label.trim()).then(|| (label.trim(), rest.trim()))
+}
+
+fn split_word(text: &str) -> (&str, &str) {
+    let text = text.trim();
+    match text.split_once(char::is_whitespace) {
+        Some((word, rest)) => (word, rest.trim()),
+        None => (text, ""),
+    }
+}
+
+fn split_operands(text: &str) -> Vec<&str> {
+    if text.trim().is_empty() {
+        return Vec::new();
+    }
+    text.split(',').map(str::trim).collect()
+}
+
+fn is_symbol_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '.'
+}
+
+fn is_symbol(name: &str) -> bool {
+    name.chars().all(is_symbol_char) && name.chars().next().is_some_and(|c| !c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests;
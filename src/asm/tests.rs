@@ -0,0 +1,139 @@
+// Sources are assembled from strings under a made up path, so nothing is read
+// from disk except the bundled ROM used for the round trip
+
+use super::*;
+use crate::disasm::{self, Syntax};
+use crate::quirks::Platform;
+
+fn assemble_text(source: &str) -> Result<Vec<u8>, AsmError> {
+    Ok(assemble(source, Path::new("test.asm"))?.rom)
+}
+
+fn error(source: &str) -> String {
+    match assemble_text(source) {
+        Ok(rom) => panic!("assembled to {:02X?}", rom),
+        Err(e) => e.to_string(),
+    }
+}
+
+#[test]
+fn module_example() {
+    let source = "
+        SPEED equ 2             ; a constant
+        main:                   ; a label
+            LD V0, SPEED
+            CALL draw
+            JP main
+        draw:
+            LD I, sprite
+            DRW V0, V1, 3
+            RET
+        sprite:
+            db 0b11100000, 0xA0, 0xE0
+            dw 0x1234
+    ";
+    let expected = [
+        0x60, 0x02, 0x22, 0x06, 0x12, 0x00, 0xA2, 0x0C, 0xD0, 0x13, 0x00, 0xEE, 0xE0, 0xA0, 0xE0,
+        0x12, 0x34,
+    ];
+    assert_eq!(assemble_text(source).unwrap(), expected);
+}
+
+// Disassembles `rom` and assembles the listing again
+fn reassemble(rom: &[u8]) -> Vec<u8> {
+    let listing = disasm::disassemble_rom(rom, Platform::CosmacVip, Syntax::Cowgod);
+    // Drops the address and bytes columns in front of each instruction
+    let source: String = listing
+        .lines()
+        .map(|line| match line.strip_prefix("    ") {
+            Some(rest) => format!("{}\n", &rest[20..]),
+            None => format!("{}\n", line),
+        })
+        .collect();
+    assemble_text(&source).unwrap()
+}
+
+#[test]
+fn reassembles_a_disassembled_rom() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("roms/BRIX");
+    let rom = fs::read(path).unwrap();
+    assert_eq!(reassemble(&rom), rom);
+}
+
+#[test]
+fn reassembles_labels_inside_instructions() {
+    // Points I at the second byte of the jump, and jumps into the middle of
+    // LD V0, 0x12 to run 12 04 as JP 0x204
+    let rom = [0xA2, 0x03, 0x12, 0x05, 0x60, 0x12, 0x04];
+    assert_eq!(reassemble(&rom), rom);
+}
+
+#[test]
+fn long_loads_take_four_bytes() {
+    let source = "
+        LD I, long far
+        LD I, long 0xFFFF
+        after: JP after
+        far equ 0x1234
+    ";
+    let expected = [0xF0, 0x00, 0x12, 0x34, 0xF0, 0x00, 0xFF, 0xFF, 0x12, 0x08];
+    assert_eq!(assemble_text(source).unwrap(), expected);
+    assert!(error("LD I, long 0x10000").contains("out of range"));
+}
+
+#[test]
+fn macros_check_their_arguments() {
+    let source = "
+        macro point x, y
+            LD V0, x
+            LD V1, y
+        endm
+        point 1, 2
+        point 3
+    ";
+    assert_eq!(
+        error(source),
+        "test.asm:7: Macro point takes 2 arguments, got 1"
+    );
+}
+
+#[test]
+fn labels_are_defined_once() {
+    let source = "
+        main: CLS
+        main: RET
+    ";
+    assert_eq!(error(source), "test.asm:3: main is already defined");
+    assert_eq!(
+        error("SPEED equ 1\nSPEED equ 2"),
+        "test.asm:2: SPEED is already defined"
+    );
+}
+
+#[test]
+fn constants_cannot_refer_to_themselves() {
+    assert!(error("LOOP equ LOOP + 1\nLD V0, LOOP").contains("refers back to itself"));
+    assert!(error("A equ B\nB equ A\nLD V0, A").contains("refers back to itself"));
+}
+
+#[test]
+fn overflowing_expressions_are_errors() {
+    assert!(error("dw 0x7FFFFFFFFFFFFFFF + 1").contains("too large"));
+    assert!(error("dw 0 - 0x7FFFFFFFFFFFFFFF - 2").contains("too large"));
+}
+
+#[test]
+fn source_map_round_trip() {
+    let source = "main:\n    CLS\n\n    JP main\n";
+    let map = assemble(source, Path::new("dir/test.asm"))
+        .unwrap()
+        .source_map;
+    let text = map.to_text();
+    assert_eq!(text, "0x0200 dir/test.asm:2\n0x0202 dir/test.asm:4\n");
+
+    let parsed = SourceMap::parse(&text).unwrap();
+    assert_eq!(parsed.to_text(), text);
+    assert_eq!(parsed.lookup(0x202), map.lookup(0x202));
+    assert!(SourceMap::parse("0x0200 test.asm").is_err());
+    assert!(SourceMap::parse("0x0200 test.asm:0").is_err());
+}
@@ -0,0 +1,169 @@
+/*
+    Sound generation.
+
+    CHIP-8 only has a buzzer that sounds while the sound timer is non-zero, so the
+    core just reports whether it should be on. XO-CHIP programs can instead load a
+    16-byte pattern of 1-bit samples and choose the rate it is played back at.
+    `Synth` turns either of these into samples for whatever audio device the
+    frontend has opened.
+*/
+
+use crate::processor::AUDIO_PATTERN_SIZE;
+use std::f32::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+
+const PATTERN_BITS: f32 = (AUDIO_PATTERN_SIZE * 8) as f32;
+
+/// What the machine wants the speaker to be doing, sampled once per frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sound {
+    pub playing: bool,
+    pub pattern: Option<[u8; AUDIO_PATTERN_SIZE]>,
+    pub pitch: u8,
+}
+
+impl Sound {
+    /// Bits of the audio pattern played per second for this pitch.
+    pub fn pattern_rate(&self) -> f32 {
+        4000.0 * 2f32.powf((self.pitch as f32 - 64.0) / 48.0)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Waveform {
+    Square,
+    Sine,
+    Triangle,
+}
+
+impl Waveform {
+    // Amplitude in -1..=1 at a phase in 0..1
+    fn sample(self, phase: f32) -> f32 {
+        match self {
+            Waveform::Square => {
+                if phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Waveform::Sine => (phase * 2.0 * PI).sin(),
+            Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+        }
+    }
+}
+
+impl fmt::Display for Waveform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Waveform::Square => "square",
+            Waveform::Sine => "sine",
+            Waveform::Triangle => "triangle",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Waveform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "square" => Ok(Waveform::Square),
+            "sine" => Ok(Waveform::Sine),
+            "triangle" => Ok(Waveform::Triangle),
+            _ => Err(format!(
+                "Unknown waveform '{}', expected one of: square, sine, triangle",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AudioSettings {
+    /// Frequency of the buzzer tone in Hz.
+    pub frequency: f32,
+    pub waveform: Waveform,
+    /// Output level from 0.0 (silent) to 1.0 (full scale).
+    pub volume: f32,
+    pub muted: bool,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        AudioSettings {
+            frequency: 440.0,
+            waveform: Waveform::Square,
+            volume: 0.25,
+            muted: false,
+        }
+    }
+}
+
+/// Produces mono `f32` samples for the current `Sound`.
+pub struct Synth {
+    settings: AudioSettings,
+    sample_rate: f32,
+    sound: Sound,
+    // Position within the current tone period, or within the audio pattern
+    // (counted in bits) when one is loaded
+    phase: f32,
+}
+
+impl Synth {
+    pub fn new(settings: AudioSettings, sample_rate: u32) -> Synth {
+        Synth {
+            settings,
+            sample_rate: sample_rate as f32,
+            sound: Sound {
+                playing: false,
+                pattern: None,
+                pitch: 64,
+            },
+            phase: 0.0,
+        }
+    }
+
+    pub fn settings(&self) -> &AudioSettings {
+        &self.settings
+    }
+
+    pub fn settings_mut(&mut self) -> &mut AudioSettings {
+        &mut self.settings
+    }
+
+    pub fn set_sound(&mut self, sound: Sound) {
+        self.sound = sound;
+    }
+
+    /// Fills `out` with the next samples.
+    pub fn fill(&mut self, out: &mut [f32]) {
+        if !self.sound.playing || self.settings.muted {
+            out.fill(0.0);
+            self.phase = 0.0;
+            return;
+        }
+
+        let volume = self.settings.volume.clamp(0.0, 1.0);
+        match self.sound.pattern {
+            Some(pattern) => {
+                let step = self.sound.pattern_rate() / self.sample_rate;
+                for sample in out.iter_mut() {
+                    let bit = self.phase as usize;
+                    let on = (pattern[bit / 8] >> (7 - bit % 8)) & 1 == 1;
+                    *sample = if on { volume } else { -volume };
+                    self.phase = (self.phase + step) % PATTERN_BITS;
+                }
+            }
+            None => {
+                let step = self.settings.frequency / self.sample_rate;
+                for sample in out.iter_mut() {
+                    *sample = self.settings.waveform.sample(self.phase) * volume;
+                    self.phase = (self.phase + step) % 1.0;
+                }
+            }
+        }
+    }
+}
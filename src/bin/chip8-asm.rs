@@ -0,0 +1,63 @@
+use chip8_emulator::asm;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+const USAGE: &str = "Usage: chip8-asm [options] <source>
+
+Assembles a CHIP-8 program into a ROM, along with a source map the
+emulator's debugger uses to show source lines while stepping. The map is
+written next to the ROM with a .map extension.
+
+Options:
+    -o, --output <file>    where to write the ROM (default: the source
+                           file with a .ch8 extension)
+    --no-map               don't write a source map
+    -h, --help             show this message";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mut source = None;
+    let mut output = None;
+    let mut write_map = true;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            "--no-map" => write_map = false,
+            "-o" | "--output" => match args.next() {
+                Some(value) => output = Some(PathBuf::from(value)),
+                None => exit_with(format!("Missing value for {}", arg)),
+            },
+            _ if arg.starts_with('-') => exit_with(format!("Unknown option {}", arg)),
+            _ if source.is_none() => source = Some(Path::new(arg)),
+            _ => exit_with(format!("Unexpected argument '{}'", arg)),
+        }
+    }
+
+    let Some(source) = source else {
+        exit_with(format!("No source file given\n\n{}", USAGE));
+    };
+    let assembly = asm::assemble_file(source).unwrap_or_else(|e| exit_with(e.to_string()));
+    let output = output.unwrap_or_else(|| source.with_extension("ch8"));
+    if let Err(e) = fs::write(&output, &assembly.rom) {
+        exit_with(format!("Unable to write {}: {}", output.display(), e));
+    }
+    if write_map {
+        let map = output.with_extension("map");
+        if let Err(e) = fs::write(&map, assembly.source_map.to_text()) {
+            exit_with(format!("Unable to write {}: {}", map.display(), e));
+        }
+    }
+    println!("Wrote {} bytes to {}", assembly.rom.len(), output.display());
+}
+
+fn exit_with(message: String) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
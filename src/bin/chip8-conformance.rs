@@ -0,0 +1,255 @@
+use chip8_emulator::framebuffer::Framebuffer;
+use chip8_emulator::headless::Headless;
+use chip8_emulator::{Platform, Processor};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+const USAGE: &str = "Usage: chip8-conformance [options] <directory>
+
+Runs the community test ROMs from Timendus' CHIP-8 test suite headlessly
+and prints a pass/fail table. The ROMs are looked for in <directory> under
+their names in the suite. Each test draws a tick or a cross next to every
+check it makes, and those marks are read off its final screen: a test
+passes when it shows ticks and no crosses. When it fails, or no marks can
+be found, its screen is left in <directory> as <rom>.screen.
+
+Options:
+    -h, --help    show this message";
+
+// Where the quirks and keypad tests look for a menu choice, so they start
+// without waiting for one
+const MENU_CHOICE: usize = 0x1FF;
+
+// The marks the suite draws for a check that passed and one that failed, as
+// `Framebuffer::to_text` shows them
+const TICK: &str = "
+....#
+...#.
+#.#..
+.#...
+";
+const CROSS: &str = "
+#.#
+.#.
+#.#
+";
+
+struct Test {
+    rom: &'static str,
+    variant: Option<&'static str>,
+    platform: Platform,
+    menu_choice: Option<u8>,
+    keys: &'static str,
+    frames: u64,
+}
+
+const TESTS: &[Test] = &[
+    Test {
+        rom: "3-corax+.ch8",
+        variant: None,
+        platform: Platform::CosmacVip,
+        menu_choice: None,
+        keys: "",
+        frames: 120,
+    },
+    Test {
+        rom: "4-flags.ch8",
+        variant: None,
+        platform: Platform::CosmacVip,
+        menu_choice: None,
+        keys: "",
+        frames: 120,
+    },
+    Test {
+        rom: "5-quirks.ch8",
+        variant: Some("chip8"),
+        platform: Platform::CosmacVip,
+        menu_choice: Some(1),
+        keys: "",
+        frames: 900,
+    },
+    Test {
+        rom: "5-quirks.ch8",
+        variant: Some("schip"),
+        platform: Platform::SuperChip,
+        menu_choice: Some(2),
+        keys: "",
+        frames: 900,
+    },
+    Test {
+        rom: "5-quirks.ch8",
+        variant: Some("xochip"),
+        platform: Platform::XoChip,
+        menu_choice: Some(3),
+        keys: "",
+        frames: 900,
+    },
+    // Presses and releases 5, which should be reported only once released
+    Test {
+        rom: "6-keypad.ch8",
+        variant: Some("fx0a"),
+        platform: Platform::CosmacVip,
+        menu_choice: Some(3),
+        keys: "30:5 40:-",
+        frames: 120,
+    },
+];
+
+enum Outcome {
+    Pass { ticks: usize },
+    Fail { ticks: usize, crosses: usize },
+    NoMarks,
+    Missing,
+    Error(String),
+}
+
+fn main() {
+    let mut directory = None;
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if arg.starts_with('-') => exit_with(format!("Unknown option {}", arg)),
+            _ if directory.is_none() => directory = Some(PathBuf::from(arg)),
+            _ => exit_with(format!("Unexpected argument '{}'", arg)),
+        }
+    }
+    let Some(directory) = directory else {
+        exit_with(format!("No directory given\n\n{}", USAGE));
+    };
+
+    println!("{:<28} {:<10} Result", "Test", "Platform");
+    let mut failures = 0;
+    for test in TESTS {
+        let outcome = check(test, &directory);
+        let result = match &outcome {
+            Outcome::Pass { ticks } => format!("PASS ({} checks)", ticks),
+            Outcome::Fail { ticks, crosses } => {
+                format!("FAIL ({} of {} checks)", crosses, ticks + crosses)
+            }
+            Outcome::NoMarks => "FAIL (no results on screen)".to_string(),
+            Outcome::Missing => "MISSING".to_string(),
+            Outcome::Error(e) => format!("ERROR: {}", e),
+        };
+        if !matches!(outcome, Outcome::Pass { .. } | Outcome::Missing) {
+            failures += 1;
+        }
+        println!(
+            "{:<28} {:<10} {}",
+            test.name(),
+            test.platform.name(),
+            result
+        );
+    }
+    if failures > 0 {
+        println!(
+            "\n{} failed. Their screens are in {} as .screen files.",
+            failures,
+            directory.display()
+        );
+        process::exit(1);
+    }
+}
+
+impl Test {
+    fn name(&self) -> String {
+        match self.variant {
+            Some(variant) => format!("{} ({})", self.rom, variant),
+            None => self.rom.to_string(),
+        }
+    }
+
+    fn stem(&self) -> String {
+        let stem = self.rom.trim_end_matches(".ch8");
+        match self.variant {
+            Some(variant) => format!("{}-{}", stem, variant),
+            None => stem.to_string(),
+        }
+    }
+}
+
+fn check(test: &Test, directory: &Path) -> Outcome {
+    let rom = directory.join(test.rom);
+    if !rom.exists() {
+        return Outcome::Missing;
+    }
+    let screen = match run(test, &rom) {
+        Ok(screen) => screen,
+        Err(e) => return Outcome::Error(e),
+    };
+    let ticks = count_marks(&screen, TICK);
+    let crosses = count_marks(&screen, CROSS);
+    let outcome = match (ticks, crosses) {
+        (0, 0) => Outcome::NoMarks,
+        (ticks, 0) => return Outcome::Pass { ticks },
+        (ticks, crosses) => Outcome::Fail { ticks, crosses },
+    };
+    let path = directory.join(format!("{}.screen", test.stem()));
+    if let Err(e) = fs::write(&path, screen.to_text()) {
+        return Outcome::Error(format!("Unable to write {}: {}", path.display(), e));
+    }
+    outcome
+}
+
+// Runs the test and returns its final screen
+fn run(test: &Test, rom: &Path) -> Result<Framebuffer, String> {
+    let mut processor = Processor::with_platform(test.platform, test.platform.quirks());
+    processor.seed_rng(0);
+    processor
+        .load_rom(&rom.to_string_lossy())
+        .map_err(|e| format!("Unable to load {}: {}", rom.display(), e))?;
+    if let Some(choice) = test.menu_choice {
+        processor
+            .poke(MENU_CHOICE, &[choice])
+            .map_err(|e| e.to_string())?;
+    }
+    let headless = Headless {
+        keys: test.keys.parse()?,
+        frames: Some(test.frames),
+        until: None,
+    };
+    headless.run(&mut processor).map_err(|e| e.to_string())?;
+    Ok(processor.display().clone())
+}
+
+// Counts where `mark` appears on the screen with nothing else lit right next
+// to it, so it isn't mistaken for part of a letter
+fn count_marks(screen: &Framebuffer, mark: &str) -> usize {
+    let rows: Vec<&[u8]> = mark.trim().lines().map(str::as_bytes).collect();
+    let (width, height) = (rows[0].len(), rows.len());
+    let lit = |x: isize, y: isize| {
+        (0..screen.width() as isize).contains(&x)
+            && (0..screen.height() as isize).contains(&y)
+            && screen.get(x as usize, y as usize) != 0
+    };
+    let matches_at = |left: isize, top: isize| {
+        (-1..=height as isize).all(|dy| {
+            (-1..=width as isize).all(|dx| {
+                let wanted = usize::try_from(dy)
+                    .ok()
+                    .zip(usize::try_from(dx).ok())
+                    .and_then(|(dy, dx)| rows.get(dy)?.get(dx))
+                    .is_some_and(|&c| c == b'#');
+                lit(left + dx, top + dy) == wanted
+            })
+        })
+    };
+    let mut count = 0;
+    for top in 0..screen.height() as isize {
+        for left in 0..screen.width() as isize {
+            if matches_at(left, top) {
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+fn exit_with(message: String) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
@@ -0,0 +1,74 @@
+use chip8_emulator::disasm::{self, Syntax};
+use chip8_emulator::Platform;
+use std::env;
+use std::fs;
+use std::process;
+
+const USAGE: &str = "Usage: chip8-disasm [options] <rom>
+
+Disassembles a CHIP-8 ROM, separating code from data by following every
+path the program can take from 0x200.
+
+Options:
+    --syntax <name>        cowgod or octo (default: cowgod)
+    --platform <name>      vip, chip48, schip or xochip, which decides the
+                           instructions recognised (default: vip)
+    -o, --output <file>    write to a file instead of standard output
+    -h, --help             show this message";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mut rom = None;
+    let mut syntax = Syntax::Cowgod;
+    let mut platform = Platform::CosmacVip;
+    let mut output = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            "--syntax" | "--platform" | "-o" | "--output" => {
+                let Some(value) = args.next() else {
+                    exit_with(format!("Missing value for {}", arg));
+                };
+                let result = match arg.as_str() {
+                    "--syntax" => value.parse().map(|value| syntax = value),
+                    "--platform" => value.parse().map(|value| platform = value),
+                    _ => {
+                        output = Some(value);
+                        Ok(())
+                    }
+                };
+                if let Err(e) = result {
+                    exit_with(e);
+                }
+            }
+            _ if arg.starts_with('-') => exit_with(format!("Unknown option {}", arg)),
+            _ if rom.is_none() => rom = Some(arg),
+            _ => exit_with(format!("Unexpected argument '{}'", arg)),
+        }
+    }
+
+    let Some(rom) = rom else {
+        exit_with(format!("No ROM given\n\n{}", USAGE));
+    };
+    let data =
+        fs::read(rom).unwrap_or_else(|e| exit_with(format!("Unable to read {}: {}", rom, e)));
+    let listing = disasm::disassemble_rom(&data, platform, syntax);
+    match output {
+        Some(path) => {
+            if let Err(e) = fs::write(path, listing) {
+                exit_with(format!("Unable to write {}: {}", path, e));
+            }
+        }
+        None => print!("{}", listing),
+    }
+}
+
+fn exit_with(message: String) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
@@ -0,0 +1,308 @@
+use chip8_emulator::audio::AudioSettings;
+use chip8_emulator::config::Config;
+use chip8_emulator::debugger::Breakpoint;
+use chip8_emulator::filter::FlickerFilter;
+use chip8_emulator::headless::KeyScript;
+use chip8_emulator::keymap::{ControllerLayout, Keymap};
+use chip8_emulator::palette::Palettes;
+use chip8_emulator::processor::DEFAULT_CYCLES_PER_FRAME;
+use chip8_emulator::rewind::DEFAULT_REWIND_MEMORY;
+use chip8_emulator::scaling::ScaleMode;
+use chip8_emulator::textmode::Glyphs;
+use chip8_emulator::{Platform, Quirks};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+pub const USAGE: &str = "Usage: chip8_emulator [options] <rom>
+
+<rom> is a path to a ROM file, or the name of one of the bundled ROMs.
+
+Options:
+    --platform <name>      vip, chip48, schip or xochip (default: plain CHIP-8)
+    --quirks <overrides>   comma separated quirks, e.g. shift=on,clip=off
+    --ipf <n>              instructions per frame (default: 8)
+    --scale <n>            size of a low resolution pixel in the starting window
+                           (default: 12)
+    --scaling <mode>       integer, fit or stretch, how the screen fills the window
+                           (default: integer)
+    --fullscreen           start in fullscreen
+    --filter <filter>      flicker filter: off, fade[:decay], blend[:frames] or xor
+                           (default: off, or the one in the config file)
+    --palette <palette>    classic, green, amber, gameboy, contrast, one defined in
+                           the config file, or 2 to 4 hex colours with the
+                           background first, e.g. 000000,33ff66
+    --frequency <hz>       buzzer frequency (default: 440)
+    --waveform <shape>     square, sine or triangle (default: square)
+    --volume <0-1>         buzzer volume (default: 0.25)
+    --mute                 start with the sound off
+    --config <file>        config file with key and controller bindings
+                           (default: ~/.config/chip8/config.toml)
+    --seed <n>             seed for the random number generator (default: random,
+                           or 0 with --headless)
+    --rewind <mib>         memory kept for rewinding, 0 to turn it off (default: 16)
+    --debug                start paused with a debugger prompt in the terminal
+    --record <file>        record the keys pressed into a movie file
+    --play <file>          play back a movie recorded with --record
+    --headless             run without a window, then print the screen and registers
+    --terminal             play in the terminal instead of a window
+    --glyphs <glyphs>      with --terminal, blocks or braille (default: blocks)
+    --frames <n>           stop after n frames
+    --until <breakpoint>   with --headless, stop at a breakpoint such as 0x2a0,
+                           0x2a0 if v3 == 5 or if i >= 0x300
+    --keys <script>        with --headless, keys to hold as frame:keys entries,
+                           e.g. \"60:5 64:- 120:4a\", or a file of them
+    --list-roms            list the bundled ROMs
+    -h, --help             show this message";
+
+const ROMS_DIR: &str = "roms";
+const DEFAULT_SCALE: u32 = 12;
+// Already wider than any screen, and keeps the window size well clear of
+// overflowing
+const MAX_SCALE: u32 = 100;
+// Headless runs check results, so they repeat unless given another seed
+const HEADLESS_SEED: u64 = 0;
+
+pub enum Action {
+    Run(Box<Options>),
+    ListRoms,
+    Help,
+}
+
+// Window, sound and terminal settings are unused in builds without them
+#[cfg_attr(not(all(feature = "sdl", feature = "terminal")), allow(dead_code))]
+pub struct Options {
+    pub rom: PathBuf,
+    pub platform: Platform,
+    pub quirks: Quirks,
+    pub cycles_per_frame: u32,
+    pub scale: u32,
+    pub scaling: ScaleMode,
+    pub fullscreen: bool,
+    pub filter: FlickerFilter,
+    pub palettes: Palettes,
+    pub audio: AudioSettings,
+    pub keymap: Keymap,
+    pub controllers: ControllerLayout,
+    pub seed: Option<u64>,
+    /// Bytes of history kept for rewinding.
+    pub rewind_memory: usize,
+    pub debug: bool,
+    pub record: Option<PathBuf>,
+    pub play: Option<PathBuf>,
+    pub headless: bool,
+    pub terminal: bool,
+    pub glyphs: Glyphs,
+    pub frames: Option<u64>,
+    pub until: Option<Breakpoint>,
+    pub keys: KeyScript,
+}
+
+pub fn parse(args: &[String]) -> Result<Action, String> {
+    let mut rom = None;
+    let mut platform = None;
+    let mut overrides = Vec::new();
+    let mut cycles_per_frame = DEFAULT_CYCLES_PER_FRAME;
+    let mut scale = DEFAULT_SCALE;
+    let mut scaling = ScaleMode::default();
+    let mut fullscreen = false;
+    let mut filter = None;
+    let mut palette = None;
+    let mut audio = AudioSettings::default();
+    let mut config = None;
+    let mut seed = None;
+    let mut rewind_memory = DEFAULT_REWIND_MEMORY;
+    let mut debug = false;
+    let mut record = None;
+    let mut play = None;
+    let mut headless = false;
+    let mut terminal = false;
+    let mut glyphs = None;
+    let mut frames = None;
+    let mut until = None;
+    let mut keys = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Action::Help),
+            "--list-roms" => return Ok(Action::ListRoms),
+            "--mute" => audio.muted = true,
+            "--debug" => debug = true,
+            "--headless" => headless = true,
+            "--terminal" => terminal = true,
+            "--fullscreen" => fullscreen = true,
+            _ if arg.starts_with("--") => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("Missing value for {}", arg))?;
+                match arg.as_str() {
+                    "--platform" => platform = Some(value.parse::<Platform>()?),
+                    "--quirks" => overrides.push(value),
+                    "--ipf" => cycles_per_frame = parse_value(arg, value)?,
+                    "--scale" => scale = parse_value(arg, value)?,
+                    "--scaling" => scaling = value.parse()?,
+                    "--filter" => filter = Some(value.parse()?),
+                    "--palette" => palette = Some(value),
+                    "--frequency" => audio.frequency = parse_value(arg, value)?,
+                    "--waveform" => audio.waveform = value.parse()?,
+                    "--volume" => audio.volume = parse_value(arg, value)?,
+                    "--config" => config = Some(PathBuf::from(value)),
+                    "--seed" => seed = Some(parse_value(arg, value)?),
+                    "--rewind" => {
+                        let mib: usize = parse_value(arg, value)?;
+                        rewind_memory = mib
+                            .checked_mul(1 << 20)
+                            .ok_or_else(|| format!("Invalid value '{}' for {}", value, arg))?;
+                    }
+                    "--frames" => frames = Some(parse_value(arg, value)?),
+                    "--record" => record = Some(PathBuf::from(value)),
+                    "--play" => play = Some(PathBuf::from(value)),
+                    "--until" => until = Some(value.parse()?),
+                    "--keys" => keys = Some(parse_key_script(value)?),
+                    "--glyphs" => glyphs = Some(value.parse()?),
+                    _ => return Err(format!("Unknown option {}", arg)),
+                }
+            }
+            _ if rom.is_none() => rom = Some(arg.as_str()),
+            _ => return Err(format!("Unexpected argument '{}'", arg)),
+        }
+    }
+
+    let rom = resolve_rom(rom.ok_or("No ROM given")?)?;
+    if headless && frames.is_none() && until.is_none() && play.is_none() {
+        return Err(
+            "--headless needs --frames, --until or --play to know when to stop".to_string(),
+        );
+    }
+    if record.is_some() && play.is_some() {
+        return Err("--record and --play can't be used together".to_string());
+    }
+    if !headless && (until.is_some() || keys.is_some()) {
+        return Err("--until and --keys only apply with --headless".to_string());
+    }
+    if headless && debug {
+        return Err("--debug needs a window, it can't be used with --headless".to_string());
+    }
+    if terminal && (headless || debug) {
+        return Err("--terminal can't be used with --headless or --debug".to_string());
+    }
+    if !terminal && glyphs.is_some() {
+        return Err("--glyphs only applies with --terminal".to_string());
+    }
+    let seed = seed.or(headless.then_some(HEADLESS_SEED));
+    if !(1..=MAX_SCALE).contains(&scale) {
+        return Err(format!("--scale must be between 1 and {}", MAX_SCALE));
+    }
+    if !(audio.frequency.is_finite() && audio.frequency > 0.0) {
+        return Err("--frequency must be a positive number of hertz".to_string());
+    }
+    // Headless runs only read the config they're given, so they repeat anywhere
+    let config = match config {
+        Some(path) => Config::load(&path)?,
+        None if headless => Config::default(),
+        None => Config::load_default()?,
+    };
+    let keymap = config.keymap(&rom)?;
+    let controllers = config.controllers(&rom)?;
+    let mut palettes = config.palettes(&rom)?;
+    let filter = match filter {
+        Some(filter) => filter,
+        None => config.filter(&rom)?,
+    };
+    if let Some(palette) = palette {
+        palettes.select(palette)?;
+    }
+
+    // Start from the platform preset (or plain CHIP-8 with the default quirks
+    // if none was given), then apply any per-flag overrides on top
+    let mut quirks = platform.map(Platform::quirks).unwrap_or_default();
+    for o in overrides {
+        quirks.apply_overrides(o)?;
+    }
+
+    Ok(Action::Run(Box::new(Options {
+        rom,
+        platform: platform.unwrap_or(Platform::CosmacVip),
+        quirks,
+        cycles_per_frame,
+        scale,
+        scaling,
+        fullscreen,
+        filter,
+        palettes,
+        audio,
+        keymap,
+        controllers,
+        seed,
+        rewind_memory,
+        debug,
+        record,
+        play,
+        headless,
+        terminal,
+        glyphs: glyphs.unwrap_or_default(),
+        frames,
+        until,
+        keys: keys.unwrap_or_default(),
+    })))
+}
+
+fn parse_value<T: FromStr>(arg: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value '{}' for {}", value, arg))
+}
+
+// Accepts a script or the path to a file holding one
+fn parse_key_script(value: &str) -> Result<KeyScript, String> {
+    let path = Path::new(value);
+    if path.is_file() {
+        let script = fs::read_to_string(path)
+            .map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+        return script.parse();
+    }
+    value.parse()
+}
+
+// Accepts either a path to a file or the name of a bundled ROM
+fn resolve_rom(name: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(name);
+    if path.is_file() {
+        return Ok(path);
+    }
+    let bundled = roms_dir().join(name);
+    if bundled.is_file() {
+        return Ok(bundled);
+    }
+    if path.exists() {
+        return Err(format!("'{}' is not a file", name));
+    }
+    Err(format!(
+        "ROM '{}' not found. Give a path to a ROM file, or run with --list-roms to see the bundled ones.",
+        name
+    ))
+}
+
+/// Names of the ROMs shipped in the roms directory, sorted.
+pub fn bundled_roms() -> io::Result<Vec<String>> {
+    let mut roms = fs::read_dir(roms_dir())?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_file())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    roms.sort();
+    Ok(roms)
+}
+
+// The roms directory next to the working directory, falling back to the one in
+// the source tree so `cargo run` works from anywhere
+fn roms_dir() -> PathBuf {
+    let local = Path::new(ROMS_DIR);
+    if local.is_dir() {
+        local.to_path_buf()
+    } else {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join(ROMS_DIR)
+    }
+}
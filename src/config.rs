@@ -0,0 +1,174 @@
+/*
+    The config file.
+
+    A TOML file of settings that apply to every ROM, with tables under
+    `[rom.<name>]` overriding them for one ROM, where the name is the ROM's
+    file name with or without its extension:
+
+        [keys]
+        5 = ["W", "Up"]
+
+        [rom.PONG.keys]
+        1 = "Up"
+        4 = "Down"
+
+        [rom.PONG2.controller.2]
+        C = ["dpup", "lefty-"]
+        D = ["dpdown", "lefty+"]
+
+    Plain settings such as `palette = "amber"` work the same way, at the top
+    of the file or in a ROM's table.
+
+    It lives in `chip8/config.toml` under `$XDG_CONFIG_HOME` (or `~/.config`),
+    unless the command line points somewhere else.
+*/
+
+use crate::filter::FlickerFilter;
+use crate::keymap::{ControllerLayout, Keymap};
+use crate::palette::{Palette, Palettes, Rgb};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    table: Table,
+}
+
+impl Config {
+    pub fn parse(text: &str) -> Result<Config, String> {
+        let table = text.parse::<Table>().map_err(|e| e.to_string())?;
+        if let Some(roms) = table.get("rom") {
+            let all_tables = roms
+                .as_table()
+                .is_some_and(|roms| roms.values().all(Value::is_table));
+            if !all_tables {
+                return Err("Expected [rom.<name>] tables under rom".to_string());
+            }
+        }
+        Ok(Config { table })
+    }
+
+    pub fn load(path: &Path) -> Result<Config, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+        Config::parse(&text).map_err(|e| format!("Invalid config {}: {}", path.display(), e))
+    }
+
+    /// Loads the config from its usual place, or an empty one if there isn't
+    /// a file there.
+    pub fn load_default() -> Result<Config, String> {
+        match default_path() {
+            Some(path) if path.is_file() => Config::load(&path),
+            _ => Ok(Config::default()),
+        }
+    }
+
+    /// The keymap for `rom`, from the global `[keys]` table and then the
+    /// ROM's own. Binding one of the emulator's own keys is an error.
+    pub fn keymap(&self, rom: &Path) -> Result<Keymap, String> {
+        let mut keymap = Keymap::default();
+        for table in self.sections(rom, "keys")? {
+            keymap.apply(table)?;
+        }
+        keymap.check_reserved()?;
+        Ok(keymap)
+    }
+
+    /// How controllers are laid out for `rom`, from the global `[controller]`
+    /// table and then the ROM's own.
+    pub fn controllers(&self, rom: &Path) -> Result<ControllerLayout, String> {
+        let mut layout = ControllerLayout::default();
+        for table in self.sections(rom, "controller")? {
+            layout.apply(table)?;
+        }
+        Ok(layout)
+    }
+
+    /// The presets plus any palettes defined under `[palettes]`, with the one
+    /// `palette` names for `rom` selected.
+    pub fn palettes(&self, rom: &Path) -> Result<Palettes, String> {
+        let mut palettes = Palettes::default();
+        if let Some(value) = self.table.get("palettes") {
+            let table = value
+                .as_table()
+                .ok_or("Expected [palettes] to be a table")?;
+            for (name, value) in table {
+                let expected = || "expected a list of hex colours".to_string();
+                let palette = match value {
+                    Value::String(colours) => colours.parse(),
+                    Value::Array(colours) => colours
+                        .iter()
+                        .map(|colour| colour.as_str().ok_or_else(expected)?.parse())
+                        .collect::<Result<Vec<Rgb>, _>>()
+                        .and_then(|colours| Palette::from_colours(&colours)),
+                    _ => Err(expected()),
+                }
+                .map_err(|e| format!("Invalid palette '{}': {}", name, e))?;
+                palettes.add(name, palette);
+            }
+        }
+        if let Some(value) = self.setting(rom, "palette") {
+            let name = value.as_str().ok_or("Expected palette to be a name")?;
+            palettes.select(name)?;
+        }
+        Ok(palettes)
+    }
+
+    /// The flicker filter `filter` names for `rom`, off if none does.
+    pub fn filter(&self, rom: &Path) -> Result<FlickerFilter, String> {
+        match self.setting(rom, "filter") {
+            Some(value) => value
+                .as_str()
+                .ok_or("Expected filter to be a name")?
+                .parse(),
+            None => Ok(FlickerFilter::default()),
+        }
+    }
+
+    // A plain setting, from the ROM's table if it has one
+    fn setting(&self, rom: &Path, name: &str) -> Option<&Value> {
+        let rom_setting = self.rom_table(rom).and_then(|(table, _)| table.get(name));
+        rom_setting.or_else(|| self.table.get(name))
+    }
+
+    // The global table called `name`, then the one for `rom`, for whichever
+    // of them exist
+    fn sections(&self, rom: &Path, name: &str) -> Result<Vec<&Table>, String> {
+        let mut tables = vec![(&self.table, name.to_string())];
+        if let Some((table, rom_name)) = self.rom_table(rom) {
+            tables.push((table, format!("rom.{}.{}", rom_name, name)));
+        }
+
+        let mut sections = Vec::new();
+        for (table, heading) in tables {
+            if let Some(value) = table.get(name) {
+                let section = value
+                    .as_table()
+                    .ok_or_else(|| format!("Expected [{}] to be a table", heading))?;
+                sections.push(section);
+            }
+        }
+        Ok(sections)
+    }
+
+    // The `[rom.<name>]` table for `rom` and the name it was found under
+    fn rom_table(&self, rom: &Path) -> Option<(&Table, String)> {
+        let roms = self.table.get("rom")?.as_table()?;
+        [rom.file_name(), rom.file_stem()]
+            .into_iter()
+            .flatten()
+            .map(|rom_name| rom_name.to_string_lossy().into_owned())
+            .find_map(|rom_name| Some((roms.get(&rom_name)?.as_table()?, rom_name)))
+    }
+}
+
+/// Where the config file is looked for when none is given.
+pub fn default_path() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_home.join("chip8").join("config.toml"))
+}
@@ -0,0 +1,760 @@
+/*
+    Pausing, stepping, breakpoints and watchpoints.
+
+    The `Debugger` only holds what the user has asked for: whether the machine
+    is paused, the breakpoints and watchpoints, and a pending step.
+    `Processor::run_frame` consults it before every instruction, the processor
+    tells it about every memory access while watchpoints are set, and
+    `Processor::debug` carries out a `DebugCommand`, returning text to show the
+    user. Commands can be typed at a
+    prompt (see `DebugCommand::from_str`) or sent from hotkeys.
+*/
+
+use crate::asm::SourceMap;
+use crate::disasm;
+use crate::processor::Processor;
+use crate::quirks::Platform;
+use std::fmt;
+use std::str::FromStr;
+
+pub const HELP: &str = "Debugger commands:
+    p, pause                  pause the program
+    c, continue               carry on running
+    s, step                   run one instruction
+    n, next                   run one instruction, running calls to completion
+    r, regs                   show the registers and the next instruction
+    b <addr> [if <cond>]      break when pc reaches an address
+    b if <cond>               break when a condition becomes true
+    bl, breakpoints           list breakpoints
+    d <n>, delete <n>         remove breakpoint n
+    w <addr>[-<end>] [rwx]    stop after memory is read, written or executed
+                              (default: w)
+    wl, watchpoints           list watchpoints
+    wd <n>                    remove watchpoint n
+    m <addr> [len]            show memory
+    h, help                   show this message
+
+Conditions compare v0-vf, i, pc, sp, dt or st with a number,
+e.g. v3 == 5 or i >= 0x300. Numbers are decimal, or hex with 0x.";
+
+// Bytes shown by a memory dump when no length is given, and per line
+const DEFAULT_DUMP_LENGTH: usize = 64;
+const DUMP_LINE_LENGTH: usize = 16;
+
+/// Something a condition can look at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Register {
+    V(usize),
+    I,
+    Pc,
+    Sp,
+    DelayTimer,
+    SoundTimer,
+}
+
+impl Register {
+    pub fn value(self, processor: &Processor) -> usize {
+        match self {
+            Register::V(x) => processor.v()[x] as usize,
+            Register::I => processor.i_register(),
+            Register::Pc => processor.pc(),
+            Register::Sp => processor.sp(),
+            Register::DelayTimer => processor.delay_timer() as usize,
+            Register::SoundTimer => processor.sound_timer() as usize,
+        }
+    }
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Register::V(x) => write!(f, "v{:x}", x),
+            Register::I => write!(f, "i"),
+            Register::Pc => write!(f, "pc"),
+            Register::Sp => write!(f, "sp"),
+            Register::DelayTimer => write!(f, "dt"),
+            Register::SoundTimer => write!(f, "st"),
+        }
+    }
+}
+
+impl FromStr for Register {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_ascii_lowercase();
+        let register = match s.as_str() {
+            "i" => Register::I,
+            "pc" => Register::Pc,
+            "sp" => Register::Sp,
+            "dt" => Register::DelayTimer,
+            "st" => Register::SoundTimer,
+            _ => match s.strip_prefix('v').map(|x| usize::from_str_radix(x, 16)) {
+                Some(Ok(x)) if x < 16 => Register::V(x),
+                _ => return Err(format!("Unknown register '{}'", s)),
+            },
+        };
+        Ok(register)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Comparison {
+    // Longest first, so that <= is not read as <
+    const SYMBOLS: [(&'static str, Comparison); 6] = [
+        ("==", Comparison::Equal),
+        ("!=", Comparison::NotEqual),
+        ("<=", Comparison::LessOrEqual),
+        (">=", Comparison::GreaterOrEqual),
+        ("<", Comparison::Less),
+        (">", Comparison::Greater),
+    ];
+
+    fn holds(self, left: usize, right: usize) -> bool {
+        match self {
+            Comparison::Equal => left == right,
+            Comparison::NotEqual => left != right,
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+            Comparison::Greater => left > right,
+            Comparison::GreaterOrEqual => left >= right,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        Comparison::SYMBOLS
+            .iter()
+            .find(|(_, comparison)| *comparison == self)
+            .unwrap()
+            .0
+    }
+}
+
+/// A test on a register, such as `v3 == 5`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Condition {
+    pub register: Register,
+    pub comparison: Comparison,
+    pub value: usize,
+}
+
+impl Condition {
+    pub fn holds(&self, processor: &Processor) -> bool {
+        self.comparison
+            .holds(self.register.value(processor), self.value)
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {:#X}",
+            self.register,
+            self.comparison.symbol(),
+            self.value
+        )
+    }
+}
+
+impl FromStr for Condition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        for (symbol, comparison) in Comparison::SYMBOLS {
+            if let Some((register, value)) = s.split_once(symbol) {
+                return Ok(Condition {
+                    register: register.trim().parse()?,
+                    comparison,
+                    value: parse_number(value.trim())?,
+                });
+            }
+        }
+        Err(format!(
+            "Expected a condition like v3 == 5, got '{}'",
+            s.trim()
+        ))
+    }
+}
+
+/// Where and when to stop. With an address the program stops every time it is
+/// about to run that instruction while the condition (if any) holds. With only
+/// a condition it stops when the condition goes from false to true.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub address: Option<usize>,
+    pub condition: Option<Condition>,
+}
+
+impl Breakpoint {
+    // True if there is no condition
+    fn condition_holds(&self, processor: &Processor) -> bool {
+        self.condition
+            .is_none_or(|condition| condition.holds(processor))
+    }
+}
+
+impl FromStr for Breakpoint {
+    type Err = String;
+
+    /// Parses `0x2a0`, `0x2a0 if v3 == 5` or `if v3 == 5`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (address, condition) = match s.strip_prefix("if ") {
+            Some(condition) => (None, Some(condition)),
+            None => match s.split_once(" if ") {
+                Some((address, condition)) => (Some(address), Some(condition)),
+                None => (Some(s), None),
+            },
+        };
+        Ok(Breakpoint {
+            address: address.map(parse_number).transpose()?,
+            condition: condition.map(str::parse).transpose()?,
+        })
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.address, self.condition) {
+            (Some(address), Some(condition)) => {
+                write!(f, "at {:#05X} if {}", address, condition)
+            }
+            (Some(address), None) => write!(f, "at {:#05X}", address),
+            (None, Some(condition)) => write!(f, "when {}", condition),
+            (None, None) => write!(f, "never"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    /// Fetched as an instruction, or as the address following `F000`.
+    Execute,
+}
+
+/// The instruction making a memory access, and the platform that decides
+/// what it does.
+pub(crate) struct Accessor {
+    pub(crate) pc: usize,
+    pub(crate) opcode: u16,
+    pub(crate) platform: Platform,
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Access::Read => "Read",
+            Access::Write => "Write",
+            Access::Execute => "Execute",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Stops the program after an instruction accesses any address from `start`
+/// to `end` inclusive in one of the chosen ways.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub start: usize,
+    pub end: usize,
+    pub read: bool,
+    pub write: bool,
+    pub execute: bool,
+}
+
+impl Watchpoint {
+    pub fn watches(&self, access: Access) -> bool {
+        match access {
+            Access::Read => self.read,
+            Access::Write => self.write,
+            Access::Execute => self.execute,
+        }
+    }
+}
+
+impl fmt::Display for Watchpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.start == self.end {
+            write!(f, "{:#05X}", self.start)?;
+        } else {
+            write!(f, "{:#05X}-{:#05X}", self.start, self.end)?;
+        }
+        let accesses: String = [(self.read, 'r'), (self.write, 'w'), (self.execute, 'x')]
+            .iter()
+            .filter(|(on, _)| *on)
+            .map(|(_, letter)| letter)
+            .collect();
+        write!(f, " ({})", accesses)
+    }
+}
+
+impl FromStr for Watchpoint {
+    type Err = String;
+
+    /// Parses an address or an inclusive range such as `0x300-0x30f`,
+    /// optionally followed by any of r, w and x.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (range, accesses) = s.split_once(' ').unwrap_or((s, "w"));
+        let (start, end) = match range.split_once('-') {
+            Some((start, end)) => (parse_number(start)?, parse_number(end)?),
+            None => (parse_number(range)?, parse_number(range)?),
+        };
+        if end < start {
+            return Err(format!("The end of {} comes before the start", range));
+        }
+        let accesses = accesses.trim().to_ascii_lowercase();
+        if accesses.is_empty() || !accesses.chars().all(|c| "rwx".contains(c)) {
+            return Err(format!(
+                "Expected any of r, w and x for a watchpoint, got '{}'",
+                accesses
+            ));
+        }
+        Ok(Watchpoint {
+            start,
+            end,
+            read: accesses.contains('r'),
+            write: accesses.contains('w'),
+            execute: accesses.contains('x'),
+        })
+    }
+}
+
+/// Everything the debugger can be asked to do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DebugCommand {
+    Pause,
+    Continue,
+    /// Pause if running, continue if paused. Handy for a single hotkey.
+    TogglePause,
+    Step,
+    /// Like `Step`, but a subroutine call runs until it returns.
+    StepOver,
+    Registers,
+    Break(Breakpoint),
+    Breakpoints,
+    Delete(usize),
+    Watch(Watchpoint),
+    Watchpoints,
+    Unwatch(usize),
+    Memory {
+        address: usize,
+        length: usize,
+    },
+    Help,
+}
+
+impl DebugCommand {
+    /// Whether the command pauses, resumes or steps the program, or sets up
+    /// something that will stop it.
+    pub fn controls_run(&self) -> bool {
+        matches!(
+            self,
+            DebugCommand::Pause
+                | DebugCommand::Continue
+                | DebugCommand::TogglePause
+                | DebugCommand::Step
+                | DebugCommand::StepOver
+                | DebugCommand::Break(_)
+                | DebugCommand::Watch(_)
+        )
+    }
+}
+
+impl FromStr for DebugCommand {
+    type Err = String;
+
+    /// Parses a line typed at the debugger prompt. See `HELP` for the syntax.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (word, rest) = s.split_once(' ').unwrap_or((s, ""));
+        let rest = rest.trim();
+        let command = match word {
+            "p" | "pause" => DebugCommand::Pause,
+            "c" | "continue" => DebugCommand::Continue,
+            "s" | "step" => DebugCommand::Step,
+            "n" | "next" => DebugCommand::StepOver,
+            "r" | "regs" => DebugCommand::Registers,
+            "bl" | "breakpoints" => DebugCommand::Breakpoints,
+            "h" | "help" | "?" => DebugCommand::Help,
+            "b" | "break" => DebugCommand::Break(rest.parse()?),
+            "d" | "delete" => DebugCommand::Delete(parse_number(rest)?),
+            "w" | "watch" => DebugCommand::Watch(rest.parse()?),
+            "wl" | "watchpoints" => DebugCommand::Watchpoints,
+            "wd" | "unwatch" => DebugCommand::Unwatch(parse_number(rest)?),
+            "m" | "mem" => {
+                let (address, length) = rest.split_once(' ').unwrap_or((rest, ""));
+                DebugCommand::Memory {
+                    address: parse_number(address)?,
+                    length: match length.trim() {
+                        "" => DEFAULT_DUMP_LENGTH,
+                        length => parse_number(length)?,
+                    },
+                }
+            }
+            _ => return Err(format!("Unknown command '{}', type help for a list", s)),
+        };
+        Ok(command)
+    }
+}
+
+// Decimal, or hex with a 0x prefix
+fn parse_number(s: &str) -> Result<usize, String> {
+    let parsed = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    parsed.map_err(|_| format!("Expected a number, got '{}'", s))
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Debugger {
+    paused: bool,
+    // Each breakpoint, with whether its condition held last time it was checked
+    breakpoints: Vec<(Breakpoint, bool)>,
+    // Set when resuming, so a breakpoint at the current pc doesn't fire again
+    // before the instruction has run
+    resuming: bool,
+    // An instruction to run while paused
+    single_step: bool,
+    // The return address and stack depth a step over is waiting for
+    step_over: Option<(usize, usize)>,
+    watchpoints: Vec<Watchpoint>,
+    // Descriptions of the watchpoints set off by the current instruction
+    watch_hits: Vec<String>,
+    report: Option<String>,
+    source_map: Option<SourceMap>,
+}
+
+impl Debugger {
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+        self.step_over = None;
+    }
+
+    pub fn resume(&mut self) {
+        if self.paused {
+            self.paused = false;
+            self.resuming = true;
+        }
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = &Breakpoint> {
+        self.breakpoints.iter().map(|(breakpoint, _)| breakpoint)
+    }
+
+    /// Adds a breakpoint and returns its number.
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> usize {
+        self.breakpoints.push((breakpoint, false));
+        self.breakpoints.len()
+    }
+
+    /// Removes breakpoint `number`, counting from 1. Later breakpoints move down.
+    pub fn remove_breakpoint(&mut self, number: usize) -> Option<Breakpoint> {
+        if number == 0 || number > self.breakpoints.len() {
+            return None;
+        }
+        Some(self.breakpoints.remove(number - 1).0)
+    }
+
+    pub fn watchpoints(&self) -> impl Iterator<Item = &Watchpoint> {
+        self.watchpoints.iter()
+    }
+
+    /// Adds a watchpoint and returns its number.
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) -> usize {
+        self.watchpoints.push(watchpoint);
+        self.watchpoints.len()
+    }
+
+    /// Removes watchpoint `number`, counting from 1. Later watchpoints move down.
+    pub fn remove_watchpoint(&mut self, number: usize) -> Option<Watchpoint> {
+        if number == 0 || number > self.watchpoints.len() {
+            return None;
+        }
+        Some(self.watchpoints.remove(number - 1))
+    }
+
+    /// Shows the source line of each instruction in reports, for programs
+    /// built with the assembler.
+    pub fn set_source_map(&mut self, source_map: SourceMap) {
+        self.source_map = Some(source_map);
+    }
+
+    pub fn source_map(&self) -> Option<&SourceMap> {
+        self.source_map.as_ref()
+    }
+
+    /// Takes the text describing why the program last stopped, if it has
+    /// stopped since the last call.
+    pub fn take_report(&mut self) -> Option<String> {
+        self.report.take()
+    }
+
+    pub(crate) fn take_single_step(&mut self) -> bool {
+        std::mem::take(&mut self.single_step)
+    }
+
+    // Reports where the program stopped, along with any watchpoints the last
+    // instruction set off
+    pub(crate) fn report_stop(&mut self, registers: String) {
+        self.watch_hits.push(registers);
+        self.report = Some(self.watch_hits.join("\n"));
+        self.watch_hits.clear();
+    }
+
+    pub(crate) fn is_watching(&self) -> bool {
+        !self.watchpoints.is_empty()
+    }
+
+    pub(crate) fn has_watch_hits(&self) -> bool {
+        !self.watch_hits.is_empty()
+    }
+
+    /// Notes an access to the memory starting at `address` by `by`. `old` is
+    /// what was there beforehand and `new` is what a write is about to store.
+    pub(crate) fn record_access(
+        &mut self,
+        access: Access,
+        by: &Accessor,
+        address: usize,
+        old: &[u8],
+        new: Option<&[u8]>,
+    ) {
+        let end = address + old.len();
+        for (number, watchpoint) in self.watchpoints.iter().enumerate() {
+            if !watchpoint.watches(access) || watchpoint.end < address || watchpoint.start >= end {
+                continue;
+            }
+            let mut hit = format!(
+                "{} watchpoint {} hit by {:#05X}: {:04X}  {}",
+                access,
+                number + 1,
+                by.pc,
+                by.opcode,
+                disasm::instruction(by.opcode, by.platform)
+            );
+            for at in watchpoint.start.max(address)..=watchpoint.end.min(end - 1) {
+                let before = old[at - address];
+                match new {
+                    Some(new) => {
+                        let after = new[at - address];
+                        hit += &format!("\n    {:#05X}: {:02X} -> {:02X}", at, before, after);
+                    }
+                    None => hit += &format!("\n    {:#05X}: {:02X}", at, before),
+                }
+            }
+            self.watch_hits.push(hit);
+        }
+    }
+
+    /// Checks whether the program should stop before running the instruction
+    /// at pc, pausing and preparing a report if so.
+    pub(crate) fn check(&mut self, processor: &Processor) -> bool {
+        if std::mem::take(&mut self.resuming) {
+            self.update_conditions(processor);
+            return false;
+        }
+
+        let mut reason = None;
+        if self.step_over == Some((processor.pc(), processor.sp())) {
+            reason = Some("Stepped over call".to_string());
+        }
+        for (number, (breakpoint, held)) in self.breakpoints.iter_mut().enumerate() {
+            let holds = breakpoint.condition_holds(processor);
+            let hit = match breakpoint.address {
+                Some(address) => address == processor.pc() && holds,
+                None => holds && !*held,
+            };
+            *held = holds;
+            if hit && reason.is_none() {
+                reason = Some(format!("Breakpoint {} hit, {}", number + 1, breakpoint));
+            }
+        }
+
+        match reason {
+            Some(reason) => {
+                self.pause();
+                self.watch_hits.push(reason);
+                let registers = describe(processor, self.source_map.as_ref());
+                self.report_stop(registers);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn update_conditions(&mut self, processor: &Processor) {
+        for (breakpoint, held) in self.breakpoints.iter_mut() {
+            *held = breakpoint.condition_holds(processor);
+        }
+    }
+}
+
+impl Processor {
+    /// Carries out a debugger command, returning text to show the user. Steps
+    /// happen on the next call to `run_frame`, which reports where they ended
+    /// up through `Debugger::take_report`.
+    pub fn debug(&mut self, command: DebugCommand) -> String {
+        match command {
+            DebugCommand::Pause => {
+                self.debugger_mut().pause();
+                format!("Paused\n{}", registers(self))
+            }
+            DebugCommand::Continue => {
+                self.debugger_mut().resume();
+                "Running".to_string()
+            }
+            DebugCommand::TogglePause if self.debugger().is_paused() => {
+                self.debug(DebugCommand::Continue)
+            }
+            DebugCommand::TogglePause => self.debug(DebugCommand::Pause),
+            DebugCommand::Step => {
+                let debugger = self.debugger_mut();
+                debugger.pause();
+                debugger.single_step = true;
+                String::new()
+            }
+            DebugCommand::StepOver => {
+                // Anything other than a 2nnn call is just a step
+                let is_call = self
+                    .ram()
+                    .get(self.pc())
+                    .is_some_and(|&high| high >> 4 == 0x2);
+                if !is_call {
+                    return self.debug(DebugCommand::Step);
+                }
+                let target = (self.pc() + 2, self.sp());
+                let debugger = self.debugger_mut();
+                debugger.paused = false;
+                debugger.resuming = true;
+                debugger.step_over = Some(target);
+                String::new()
+            }
+            DebugCommand::Registers => registers(self),
+            DebugCommand::Break(breakpoint) => {
+                let number = self.debugger_mut().add_breakpoint(breakpoint);
+                format!("Breakpoint {}: {}", number, breakpoint)
+            }
+            DebugCommand::Breakpoints => {
+                let list: Vec<String> = self
+                    .debugger()
+                    .breakpoints()
+                    .enumerate()
+                    .map(|(i, breakpoint)| format!("{}: {}", i + 1, breakpoint))
+                    .collect();
+                if list.is_empty() {
+                    "No breakpoints".to_string()
+                } else {
+                    list.join("\n")
+                }
+            }
+            DebugCommand::Delete(number) => match self.debugger_mut().remove_breakpoint(number) {
+                Some(breakpoint) => format!("Deleted breakpoint {}: {}", number, breakpoint),
+                None => format!("There is no breakpoint {}", number),
+            },
+            DebugCommand::Watch(watchpoint) => {
+                let number = self.debugger_mut().add_watchpoint(watchpoint);
+                format!("Watchpoint {}: {}", number, watchpoint)
+            }
+            DebugCommand::Watchpoints => {
+                let list: Vec<String> = self
+                    .debugger()
+                    .watchpoints()
+                    .enumerate()
+                    .map(|(i, watchpoint)| format!("{}: {}", i + 1, watchpoint))
+                    .collect();
+                if list.is_empty() {
+                    "No watchpoints".to_string()
+                } else {
+                    list.join("\n")
+                }
+            }
+            DebugCommand::Unwatch(number) => match self.debugger_mut().remove_watchpoint(number) {
+                Some(watchpoint) => format!("Deleted watchpoint {}: {}", number, watchpoint),
+                None => format!("There is no watchpoint {}", number),
+            },
+            DebugCommand::Memory { address, length } => memory_dump(self.ram(), address, length),
+            DebugCommand::Help => HELP.to_string(),
+        }
+    }
+}
+
+/// The registers, timers, stack and the instruction about to run, with its
+/// source line if the debugger has a source map.
+pub fn registers(processor: &Processor) -> String {
+    describe(processor, processor.debugger().source_map())
+}
+
+// Takes the source map separately, as `Debugger::check` runs while the
+// processor's debugger is taken out of it
+fn describe(processor: &Processor, source_map: Option<&SourceMap>) -> String {
+    let (instruction, _) =
+        disasm::disassemble(processor.ram(), processor.pc(), processor.platform());
+    let opcode = processor
+        .ram()
+        .get(processor.pc()..processor.pc() + 2)
+        .map_or(0, |bytes| (bytes[0] as u16) << 8 | bytes[1] as u16);
+    let v: Vec<String> = processor
+        .v()
+        .iter()
+        .enumerate()
+        .map(|(x, value)| format!("V{:X}={:02X}", x, value))
+        .collect();
+    let stack: Vec<String> = processor.stack()[1..=processor.sp()]
+        .iter()
+        .map(|address| format!("{:#05X}", address))
+        .collect();
+    let source = source_map
+        .and_then(|map| map.source_line(processor.pc()))
+        .map(|line| line + "\n")
+        .unwrap_or_default();
+    format!(
+        "{}{:#05X}: {:04X}  {}\n{}\n{}\nI={:#05X} DT={:02X} ST={:02X} SP={} stack=[{}]",
+        source,
+        processor.pc(),
+        opcode,
+        instruction,
+        v[..8].join(" "),
+        v[8..].join(" "),
+        processor.i_register(),
+        processor.delay_timer(),
+        processor.sound_timer(),
+        processor.sp(),
+        stack.join(" ")
+    )
+}
+
+fn memory_dump(ram: &[u8], address: usize, length: usize) -> String {
+    let end = address.saturating_add(length).min(ram.len());
+    if address >= end {
+        return format!("{:#X} is outside memory", address);
+    }
+    let lines: Vec<String> = (address..end)
+        .step_by(DUMP_LINE_LENGTH)
+        .map(|start| {
+            let bytes: Vec<String> = ram[start..end.min(start + DUMP_LINE_LENGTH)]
+                .iter()
+                .map(|byte| format!("{:02X}", byte))
+                .collect();
+            format!("{:#06X}: {}", start, bytes.join(" "))
+        })
+        .collect();
+    lines.join("\n")
+}
@@ -0,0 +1,379 @@
+/*
+    Turning machine code back into assembly.
+
+    Opcodes are decoded with `Instruction::decode`, the same table the processor
+    executes from. A whole ROM is disassembled by recursive descent: starting at
+    0x200, every path the program can take is followed through jumps, calls and
+    skips, and whatever is never reached is treated as data. Jump and call
+    targets and addresses loaded into I get labels, and data is shown as 8 pixel
+    wide sprite rows since that is what most of it is.
+
+    Two syntaxes are supported: the mnemonics from Cowgod's technical reference,
+    and Octo's, which the Octo assembler accepts back.
+*/
+
+use crate::instruction::Instruction;
+use crate::quirks::Platform;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::str::FromStr;
+
+const PROGRAM_START: usize = 0x200;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Syntax {
+    Cowgod,
+    Octo,
+}
+
+impl fmt::Display for Syntax {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Syntax::Cowgod => "cowgod",
+            Syntax::Octo => "octo",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Syntax {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "cowgod" => Ok(Syntax::Cowgod),
+            "octo" => Ok(Syntax::Octo),
+            _ => Err(format!(
+                "Unknown syntax '{}', expected one of: cowgod, octo",
+                s
+            )),
+        }
+    }
+}
+
+/// Decodes the instruction at `address` in Cowgod syntax, returning its text
+/// and its length in bytes.
+pub fn disassemble(memory: &[u8], address: usize, platform: Platform) -> (String, usize) {
+    let opcode = word(memory, address);
+    match Instruction::decode(opcode, platform) {
+        Some(instruction) => {
+            let long = word(memory, address + 2) as usize;
+            let text = format_instruction(instruction, long, Syntax::Cowgod, &hex);
+            (text, instruction.size())
+        }
+        None => (format!("DW {:#06X}", opcode), 2),
+    }
+}
+
+/// The Cowgod text for a single opcode as `platform` decodes it. `F000` is
+/// shown without the address that follows it.
+pub fn instruction(opcode: u16, platform: Platform) -> String {
+    match Instruction::decode(opcode, platform) {
+        Some(Instruction::LdILong) => "LD I, long".to_string(),
+        Some(instruction) => format_instruction(instruction, 0, Syntax::Cowgod, &hex),
+        None => format!("DW {:#06X}", opcode),
+    }
+}
+
+/// Disassembles a ROM that is loaded at 0x200.
+pub fn disassemble_rom(rom: &[u8], platform: Platform, syntax: Syntax) -> String {
+    let mut memory = vec![0; PROGRAM_START];
+    memory.extend_from_slice(rom);
+    let end = memory.len();
+    let code = trace(&memory, platform);
+    let labels = label(&memory, &code);
+    let name = |address: usize| match labels.get(&address) {
+        Some(label) => label.clone(),
+        None => hex(address),
+    };
+
+    let mut lines = Vec::new();
+    let mut printed = BTreeSet::new();
+    let mut address = PROGRAM_START;
+    while address < end {
+        if let Some(label) = labels.get(&address) {
+            printed.insert(address);
+            lines.push(match syntax {
+                Syntax::Cowgod => format!("{}:", label),
+                Syntax::Octo => format!(": {}", label),
+            });
+        }
+        let (text, size, comment) = match code.get(&address) {
+            Some(&instruction) => {
+                let long = word(&memory, address + 2) as usize;
+                let text = format_instruction(instruction, long, syntax, &name);
+                (text, instruction.size(), String::new())
+            }
+            None => {
+                let byte = memory[address];
+                let text = match syntax {
+                    Syntax::Cowgod => format!("DB {:#04X}", byte),
+                    Syntax::Octo => format!("{:#04X}", byte),
+                };
+                (text, 1, sprite_row(byte))
+            }
+        };
+        let bytes: Vec<String> = memory[address..(address + size).min(end)]
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect();
+        lines.push(match syntax {
+            Syntax::Cowgod => {
+                let line = format!("    {:#05X}  {:<12} {}", address, bytes.join(" "), text);
+                if comment.is_empty() {
+                    line
+                } else {
+                    format!("{:<40} ; {}", line, comment)
+                }
+            }
+            Syntax::Octo => format!(
+                "    {:<28} # {:#05X}  {:<12} {}",
+                text,
+                address,
+                bytes.join(" "),
+                comment
+            )
+            .trim_end()
+            .to_string(),
+        });
+        address += size;
+    }
+
+    // An address inside an instruction has no line to put its label on, so
+    // the name is defined up front instead
+    let definitions = labels
+        .iter()
+        .filter(|(address, _)| !printed.contains(address))
+        .map(|(&address, label)| match syntax {
+            Syntax::Cowgod => format!("{} equ {}", label, hex(address)),
+            Syntax::Octo => format!(":const {} {}", label, hex(address)),
+        });
+    let lines: Vec<String> = definitions.chain(lines).collect();
+    lines.join("\n") + "\n"
+}
+
+// Follows every path through the program from 0x200, returning the
+// instructions found by address
+fn trace(memory: &[u8], platform: Platform) -> BTreeMap<usize, Instruction> {
+    let mut code = BTreeMap::new();
+    let mut pending = vec![PROGRAM_START];
+    while let Some(mut address) = pending.pop() {
+        while address + 1 < memory.len() && !code.contains_key(&address) {
+            let Some(instruction) = Instruction::decode(word(memory, address), platform) else {
+                break;
+            };
+            let next = address + instruction.size();
+            if next > memory.len() {
+                break;
+            }
+            code.insert(address, instruction);
+            match instruction {
+                Instruction::Jp(target) => {
+                    pending.push(target);
+                    break;
+                }
+                // Only the first entry of a jump table is known
+                Instruction::JpV0(_, target) => {
+                    pending.push(target);
+                    break;
+                }
+                Instruction::Ret | Instruction::Exit => break,
+                Instruction::Call(target) => pending.push(target),
+                _ if instruction.is_skip() => {
+                    let skipped = Instruction::decode(word(memory, next), platform)
+                        .map_or(2, |instruction| instruction.size());
+                    pending.push(next + skipped);
+                }
+                _ => (),
+            }
+            address = next;
+        }
+    }
+    code
+}
+
+// Names the addresses that the code refers to
+fn label(memory: &[u8], code: &BTreeMap<usize, Instruction>) -> BTreeMap<usize, String> {
+    let mut calls = BTreeSet::new();
+    let mut jumps = BTreeSet::new();
+    let mut data = BTreeSet::new();
+    for (&address, &instruction) in code {
+        match instruction {
+            Instruction::Call(target) => calls.insert(target),
+            Instruction::Jp(target) | Instruction::JpV0(_, target) => jumps.insert(target),
+            Instruction::LdI(target) => data.insert(target),
+            Instruction::LdILong => data.insert(word(memory, address + 2) as usize),
+            _ => false,
+        };
+    }
+
+    // Later names take priority, and only addresses inside the ROM get one
+    let in_rom = |address: &usize| (PROGRAM_START..memory.len()).contains(address);
+    let mut labels = BTreeMap::new();
+    for address in data.iter().filter(|a| in_rom(a)) {
+        labels.insert(*address, format!("data_{:03X}", address));
+    }
+    for address in jumps.iter().filter(|a| in_rom(a)) {
+        labels.insert(*address, format!("label_{:03X}", address));
+    }
+    for address in calls.iter().filter(|a| in_rom(a)) {
+        labels.insert(*address, format!("sub_{:03X}", address));
+    }
+    labels.insert(PROGRAM_START, "main".to_string());
+    labels
+}
+
+fn format_instruction(
+    instruction: Instruction,
+    long: usize,
+    syntax: Syntax,
+    name: &dyn Fn(usize) -> String,
+) -> String {
+    match syntax {
+        Syntax::Cowgod => cowgod(instruction, long, name),
+        Syntax::Octo => octo(instruction, long, name),
+    }
+}
+
+fn cowgod(instruction: Instruction, long: usize, name: &dyn Fn(usize) -> String) -> String {
+    match instruction {
+        Instruction::Sys(nnn) => format!("SYS {}", hex(nnn)),
+        Instruction::Cls => "CLS".to_string(),
+        Instruction::Ret => "RET".to_string(),
+        Instruction::Scd(n) => format!("SCD {}", n),
+        Instruction::Scu(n) => format!("SCU {}", n),
+        Instruction::Scr => "SCR".to_string(),
+        Instruction::Scl => "SCL".to_string(),
+        Instruction::Exit => "EXIT".to_string(),
+        Instruction::Low => "LOW".to_string(),
+        Instruction::High => "HIGH".to_string(),
+        Instruction::Jp(nnn) => format!("JP {}", name(nnn)),
+        Instruction::Call(nnn) => format!("CALL {}", name(nnn)),
+        Instruction::SeByte(x, kk) => format!("SE V{:X}, {:#04X}", x, kk),
+        Instruction::SneByte(x, kk) => format!("SNE V{:X}, {:#04X}", x, kk),
+        Instruction::SeReg(x, y) => format!("SE V{:X}, V{:X}", x, y),
+        Instruction::Save(x, y) => format!("SAVE V{:X} - V{:X}", x, y),
+        Instruction::Load(x, y) => format!("LOAD V{:X} - V{:X}", x, y),
+        Instruction::LdByte(x, kk) => format!("LD V{:X}, {:#04X}", x, kk),
+        Instruction::AddByte(x, kk) => format!("ADD V{:X}, {:#04X}", x, kk),
+        Instruction::LdReg(x, y) => format!("LD V{:X}, V{:X}", x, y),
+        Instruction::Or(x, y) => format!("OR V{:X}, V{:X}", x, y),
+        Instruction::And(x, y) => format!("AND V{:X}, V{:X}", x, y),
+        Instruction::Xor(x, y) => format!("XOR V{:X}, V{:X}", x, y),
+        Instruction::AddReg(x, y) => format!("ADD V{:X}, V{:X}", x, y),
+        Instruction::Sub(x, y) => format!("SUB V{:X}, V{:X}", x, y),
+        Instruction::Shr(x, y) => format!("SHR V{:X}, V{:X}", x, y),
+        Instruction::Subn(x, y) => format!("SUBN V{:X}, V{:X}", x, y),
+        Instruction::Shl(x, y) => format!("SHL V{:X}, V{:X}", x, y),
+        Instruction::SneReg(x, y) => format!("SNE V{:X}, V{:X}", x, y),
+        Instruction::LdI(nnn) => format!("LD I, {}", name(nnn)),
+        Instruction::JpV0(_, nnn) => format!("JP V0, {}", name(nnn)),
+        Instruction::Rnd(x, kk) => format!("RND V{:X}, {:#04X}", x, kk),
+        Instruction::Drw(x, y, n) => format!("DRW V{:X}, V{:X}, {}", x, y, n),
+        Instruction::Skp(x) => format!("SKP V{:X}", x),
+        Instruction::Sknp(x) => format!("SKNP V{:X}", x),
+        Instruction::LdILong => format!("LD I, long {}", name(long)),
+        Instruction::Plane(n) => format!("PLANE {}", n),
+        Instruction::Audio => "AUDIO".to_string(),
+        Instruction::LdVxDt(x) => format!("LD V{:X}, DT", x),
+        Instruction::LdVxK(x) => format!("LD V{:X}, K", x),
+        Instruction::LdDtVx(x) => format!("LD DT, V{:X}", x),
+        Instruction::LdStVx(x) => format!("LD ST, V{:X}", x),
+        Instruction::AddIVx(x) => format!("ADD I, V{:X}", x),
+        Instruction::LdF(x) => format!("LD F, V{:X}", x),
+        Instruction::LdHf(x) => format!("LD HF, V{:X}", x),
+        Instruction::LdB(x) => format!("LD B, V{:X}", x),
+        Instruction::Pitch(x) => format!("PITCH V{:X}", x),
+        Instruction::LdIVx(x) => format!("LD [I], V{:X}", x),
+        Instruction::LdVxI(x) => format!("LD V{:X}, [I]", x),
+        Instruction::LdRVx(x) => format!("LD R, V{:X}", x),
+        Instruction::LdVxR(x) => format!("LD V{:X}, R", x),
+    }
+}
+
+// Octo writes skips as the condition under which the next instruction runs,
+// which is the opposite of the condition that skips it
+fn octo(instruction: Instruction, long: usize, name: &dyn Fn(usize) -> String) -> String {
+    match instruction {
+        // Octo has no SYS, so the opcode is written out as bytes
+        Instruction::Sys(nnn) => format!("{:#04X} {:#04X}", nnn >> 8, nnn & 0xFF),
+        Instruction::Cls => "clear".to_string(),
+        Instruction::Ret => "return".to_string(),
+        Instruction::Scd(n) => format!("scroll-down {}", n),
+        Instruction::Scu(n) => format!("scroll-up {}", n),
+        Instruction::Scr => "scroll-right".to_string(),
+        Instruction::Scl => "scroll-left".to_string(),
+        Instruction::Exit => "exit".to_string(),
+        Instruction::Low => "lores".to_string(),
+        Instruction::High => "hires".to_string(),
+        Instruction::Jp(nnn) => format!("jump {}", name(nnn)),
+        Instruction::Call(nnn) => {
+            let target = name(nnn);
+            if target.starts_with("0x") {
+                format!(":call {}", target)
+            } else {
+                target
+            }
+        }
+        Instruction::SeByte(x, kk) => format!("if v{:x} != {:#04X} then", x, kk),
+        Instruction::SneByte(x, kk) => format!("if v{:x} == {:#04X} then", x, kk),
+        Instruction::SeReg(x, y) => format!("if v{:x} != v{:x} then", x, y),
+        Instruction::Save(x, y) => format!("save v{:x} - v{:x}", x, y),
+        Instruction::Load(x, y) => format!("load v{:x} - v{:x}", x, y),
+        Instruction::LdByte(x, kk) => format!("v{:x} := {:#04X}", x, kk),
+        Instruction::AddByte(x, kk) => format!("v{:x} += {:#04X}", x, kk),
+        Instruction::LdReg(x, y) => format!("v{:x} := v{:x}", x, y),
+        Instruction::Or(x, y) => format!("v{:x} |= v{:x}", x, y),
+        Instruction::And(x, y) => format!("v{:x} &= v{:x}", x, y),
+        Instruction::Xor(x, y) => format!("v{:x} ^= v{:x}", x, y),
+        Instruction::AddReg(x, y) => format!("v{:x} += v{:x}", x, y),
+        Instruction::Sub(x, y) => format!("v{:x} -= v{:x}", x, y),
+        Instruction::Shr(x, y) => format!("v{:x} >>= v{:x}", x, y),
+        Instruction::Subn(x, y) => format!("v{:x} =- v{:x}", x, y),
+        Instruction::Shl(x, y) => format!("v{:x} <<= v{:x}", x, y),
+        Instruction::SneReg(x, y) => format!("if v{:x} == v{:x} then", x, y),
+        Instruction::LdI(nnn) => format!("i := {}", name(nnn)),
+        Instruction::JpV0(_, nnn) => format!("jump0 {}", name(nnn)),
+        Instruction::Rnd(x, kk) => format!("v{:x} := random {:#04X}", x, kk),
+        Instruction::Drw(x, y, n) => format!("sprite v{:x} v{:x} {}", x, y, n),
+        Instruction::Skp(x) => format!("if v{:x} -key then", x),
+        Instruction::Sknp(x) => format!("if v{:x} key then", x),
+        Instruction::LdILong => format!("i := long {}", name(long)),
+        Instruction::Plane(n) => format!("plane {}", n),
+        Instruction::Audio => "audio".to_string(),
+        Instruction::LdVxDt(x) => format!("v{:x} := delay", x),
+        Instruction::LdVxK(x) => format!("v{:x} := key", x),
+        Instruction::LdDtVx(x) => format!("delay := v{:x}", x),
+        Instruction::LdStVx(x) => format!("buzzer := v{:x}", x),
+        Instruction::AddIVx(x) => format!("i += v{:x}", x),
+        Instruction::LdF(x) => format!("i := hex v{:x}", x),
+        Instruction::LdHf(x) => format!("i := bighex v{:x}", x),
+        Instruction::LdB(x) => format!("bcd v{:x}", x),
+        Instruction::Pitch(x) => format!("pitch := v{:x}", x),
+        Instruction::LdIVx(x) => format!("save v{:x}", x),
+        Instruction::LdVxI(x) => format!("load v{:x}", x),
+        Instruction::LdRVx(x) => format!("saveflags v{:x}", x),
+        Instruction::LdVxR(x) => format!("loadflags v{:x}", x),
+    }
+}
+
+fn hex(address: usize) -> String {
+    format!("{:#05X}", address)
+}
+
+// The big-endian word at `address`, reading zeroes past the end
+fn word(memory: &[u8], address: usize) -> u16 {
+    let high = *memory.get(address).unwrap_or(&0) as u16;
+    let low = *memory.get(address + 1).unwrap_or(&0) as u16;
+    high << 8 | low
+}
+
+// A byte drawn the way DRW would put it on screen
+fn sprite_row(byte: u8) -> String {
+    (0..8)
+        .map(|bit| if byte >> (7 - bit) & 1 == 1 { '#' } else { '.' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,89 @@
+// A ROM small enough to check every line of: a call, a skip over an XO-CHIP
+// `F000 NNNN`, a loop, and data both between code and after it
+
+use super::*;
+
+const ROM: [u8; 16] = [
+    0x22, 0x0C, // CALL sub_20C
+    0x30, 0x00, // SE V0, 0x00
+    0xF0, 0x00, 0x02, 0x0E, // LD I, long data_20E
+    0x12, 0x08, // JP label_208
+    0x3C, 0x42, // never reached, though it decodes as SE VC, 0x42
+    0x00, 0xEE, // RET
+    0xF0, 0x90, // loaded into I
+];
+
+#[test]
+fn cowgod_listing() {
+    let expected = "\
+main:
+    0x200  22 0C        CALL sub_20C
+    0x202  30 00        SE V0, 0x00
+    0x204  F0 00 02 0E  LD I, long data_20E
+label_208:
+    0x208  12 08        JP label_208
+    0x20A  3C           DB 0x3C          ; ..####..
+    0x20B  42           DB 0x42          ; .#....#.
+sub_20C:
+    0x20C  00 EE        RET
+data_20E:
+    0x20E  F0           DB 0xF0          ; ####....
+    0x20F  90           DB 0x90          ; #..#....
+";
+    assert_eq!(
+        disassemble_rom(&ROM, Platform::XoChip, Syntax::Cowgod),
+        expected
+    );
+}
+
+#[test]
+fn octo_listing() {
+    let expected = "\
+: main
+    sub_20C                      # 0x200  22 0C
+    if v0 != 0x00 then           # 0x202  30 00
+    i := long data_20E           # 0x204  F0 00 02 0E
+: label_208
+    jump label_208               # 0x208  12 08
+    0x3C                         # 0x20A  3C           ..####..
+    0x42                         # 0x20B  42           .#....#.
+: sub_20C
+    return                       # 0x20C  00 EE
+: data_20E
+    0xF0                         # 0x20E  F0           ####....
+    0x90                         # 0x20F  90           #..#....
+";
+    assert_eq!(
+        disassemble_rom(&ROM, Platform::XoChip, Syntax::Octo),
+        expected
+    );
+}
+
+#[test]
+fn defines_labels_that_fall_inside_instructions() {
+    // LD I, data_203; JP label_202, where 0x203 is the low byte of the jump
+    let rom = [0xA2, 0x03, 0x12, 0x02];
+    let cowgod = disassemble_rom(&rom, Platform::CosmacVip, Syntax::Cowgod);
+    assert!(cowgod.starts_with("data_203 equ 0x203\nmain:\n"));
+    assert!(cowgod.contains("LD I, data_203"));
+    let octo = disassemble_rom(&rom, Platform::CosmacVip, Syntax::Octo);
+    assert!(octo.starts_with(":const data_203 0x203\n: main\n"));
+}
+
+#[test]
+fn skips_step_over_the_whole_long_load() {
+    let mut memory = vec![0; PROGRAM_START];
+    memory.extend_from_slice(&ROM);
+    let code = trace(&memory, Platform::XoChip);
+    let addresses: Vec<usize> = code.keys().copied().collect();
+    assert_eq!(addresses, [0x200, 0x202, 0x204, 0x208, 0x20C]);
+}
+
+#[test]
+fn platforms_without_long_loads_stop_at_f000() {
+    let mut memory = vec![0; PROGRAM_START];
+    memory.extend_from_slice(&ROM);
+    let code = trace(&memory, Platform::SuperChip);
+    assert!(!code.contains_key(&0x204));
+    assert!(!code.contains_key(&0x20A));
+}
@@ -1,110 +1,365 @@
 extern crate sdl2;
-use crate::DISPLAY_HEIGHT;
-use crate::DISPLAY_WIDTH;
-use crate::KEYBOARD_SIZE;
-use sdl2::keyboard::Scancode;
-use sdl2::pixels::Color;
+use crate::gamepads::{self, Gamepads};
+use crate::speaker::Speaker;
+use chip8_emulator::audio::{AudioSettings, Sound};
+use chip8_emulator::debugger::DebugCommand;
+use chip8_emulator::filter::{FilterStage, FlickerFilter};
+use chip8_emulator::framebuffer::{LORES_HEIGHT, LORES_WIDTH};
+use chip8_emulator::keymap::Keymap;
+use chip8_emulator::palette::{Palettes, Rgb};
+use chip8_emulator::scaling::{self, ScaleMode};
+use chip8_emulator::{Chip8Error, Command, Framebuffer, Frontend, KEYBOARD_SIZE};
+use sdl2::event::Event;
+use sdl2::keyboard::{Mod, Scancode};
+use sdl2::messagebox::{show_simple_message_box, MessageBoxFlag};
+use sdl2::pixels::{Color, PixelFormatEnum};
 use sdl2::rect::Rect;
-use sdl2::render::Canvas;
-use sdl2::video::Window;
-use sdl2::EventPump;
+use sdl2::render::{Canvas, Texture, TextureCreator};
+use sdl2::video::{FullscreenType, Window, WindowContext};
+use sdl2::{EventPump, Sdl};
 
-const PIXEL_SCALE: u32 = 12;
+// Frames stepped back per frame when rewinding with shift held
+const FAST_REWIND_FRAMES: u32 = 4;
+// Bytes per pixel in the screen texture
+const BYTES_PER_PIXEL: usize = 3;
 
-pub struct GameCanvas {
+/// How the window starts out and draws the screen.
+pub struct WindowSettings {
+    pub title: String,
+    /// Window pixels per low resolution CHIP-8 pixel.
+    pub pixel_scale: u32,
+    pub scaling: ScaleMode,
+    pub fullscreen: bool,
+    pub filter: FlickerFilter,
+}
+
+/// SDL and the window, opened before the `GameCanvas` so that the caller can
+/// own the texture creator it draws with.
+pub struct Video {
+    sdl_context: Sdl,
     canvas: Canvas<Window>,
-    event_pump: EventPump,
 }
 
-impl GameCanvas {
-    pub fn new(title: &str) -> GameCanvas {
+impl Video {
+    pub fn open(settings: &WindowSettings) -> Video {
         let sdl_context = sdl2::init().unwrap();
         let video_subsystem = sdl_context.video().unwrap();
 
-        let window = video_subsystem
+        let mut window = video_subsystem
             .window(
-                title,
-                DISPLAY_WIDTH as u32 * PIXEL_SCALE,
-                DISPLAY_HEIGHT as u32 * PIXEL_SCALE,
+                &settings.title,
+                LORES_WIDTH as u32 * settings.pixel_scale,
+                LORES_HEIGHT as u32 * settings.pixel_scale,
             )
             .position_centered()
+            .resizable()
             .build()
             .unwrap();
+        if settings.fullscreen {
+            let _ = window.set_fullscreen(FullscreenType::Desktop);
+        }
 
+        // Scale the screen up without blurring its pixels
+        sdl2::hint::set("SDL_RENDER_SCALE_QUALITY", "nearest");
         let canvas = window.into_canvas().build().unwrap();
+        Video {
+            sdl_context,
+            canvas,
+        }
+    }
+
+    pub fn texture_creator(&self) -> TextureCreator<WindowContext> {
+        self.canvas.texture_creator()
+    }
+}
+
+pub struct GameCanvas<'a> {
+    canvas: Canvas<Window>,
+    // The framebuffer is copied into this each frame, and the renderer scales
+    // it to the window in one go. Recreated when the resolution changes.
+    screen: Option<Texture<'a>>,
+    texture_creator: &'a TextureCreator<WindowContext>,
+    scaling: ScaleMode,
+    filter: FilterStage,
+    event_pump: EventPump,
+    speaker: Option<Speaker>,
+    gamepads: Option<Gamepads>,
+    commands: Vec<Command>,
+    // Host keys and the CHIP-8 keys they press
+    keys: Vec<(Scancode, usize)>,
+    palettes: Palettes,
+}
+
+impl<'a> GameCanvas<'a> {
+    /// Takes over `video`, drawing the screen with textures from
+    /// `texture_creator`, which must come from `video`.
+    pub fn new(
+        video: Video,
+        texture_creator: &'a TextureCreator<WindowContext>,
+        settings: WindowSettings,
+        palettes: Palettes,
+        audio_settings: AudioSettings,
+        keys: Vec<(Scancode, usize)>,
+        pad_bindings: gamepads::Bindings,
+    ) -> GameCanvas<'a> {
+        let Video {
+            sdl_context,
+            canvas,
+        } = video;
         let event_pump = sdl_context.event_pump().unwrap();
+
+        // Carry on without sound rather than refusing to start
+        let speaker = match Speaker::new(&sdl_context, audio_settings) {
+            Ok(speaker) => Some(speaker),
+            Err(e) => {
+                println!("Unable to open audio device: {}", e);
+                None
+            }
+        };
+
+        // Likewise without controllers
+        let gamepads = match Gamepads::new(&sdl_context, pad_bindings) {
+            Ok(gamepads) => Some(gamepads),
+            Err(e) => {
+                println!("Unable to use game controllers: {}", e);
+                None
+            }
+        };
+
         GameCanvas {
             canvas,
-            event_pump: event_pump,
+            screen: None,
+            texture_creator,
+            scaling: settings.scaling,
+            filter: FilterStage::new(settings.filter),
+            event_pump,
+            speaker,
+            gamepads,
+            commands: Vec::new(),
+            keys,
+            palettes,
         }
     }
 
-    pub fn read_keyboard_inputs(&mut self) -> Option<[bool; KEYBOARD_SIZE]> {
-        self.event_pump.pump_events();
-        if self
-            .event_pump
-            .keyboard_state()
-            .is_scancode_pressed(Scancode::Escape)
-        {
-            return None;
+    // Copies the framebuffer into the screen texture, through the flicker
+    // filter and in the current palette
+    fn upload(&mut self, display: &Framebuffer) -> Result<(), String> {
+        let (width, height) = (display.width() as u32, display.height() as u32);
+        let resized = self.screen.as_ref().is_none_or(|screen| {
+            let query = screen.query();
+            (query.width, query.height) != (width, height)
+        });
+        if resized {
+            let texture = self
+                .texture_creator
+                .create_texture_streaming(PixelFormatEnum::RGB24, width, height)
+                .map_err(|e| e.to_string())?;
+            self.screen = Some(texture);
         }
 
-        let mut keys = [false; KEYBOARD_SIZE];
+        let colours = self.filter.apply(display, &self.palettes.current());
+        let screen = self
+            .screen
+            .as_mut()
+            .expect("the screen texture exists by now");
+        screen.with_lock(None, |buffer, pitch| {
+            for (row, colours) in colours.chunks(display.width()).enumerate() {
+                let line = &mut buffer[row * pitch..];
+                for (col, &Rgb(r, g, b)) in colours.iter().enumerate() {
+                    let at = col * BYTES_PER_PIXEL;
+                    line[at..at + BYTES_PER_PIXEL].copy_from_slice(&[r, g, b]);
+                }
+            }
+        })
+    }
+}
 
-        // The 4x4 keyboard will be mapped to the following keys
-        /*
-            1 2 3 C           1 2 3 4
-            4 5 6 D  --->     q w e r
-            7 8 9 E           a s d f
-            A 0 B F           z x c v
-        */
-        for s in self.event_pump.keyboard_state().pressed_scancodes() {
-            let code = match s {
-                Scancode::X => Some(0x0),
-                Scancode::Num1 => Some(0x1),
-                Scancode::Num2 => Some(0x2),
-                Scancode::Num3 => Some(0x3),
-                Scancode::Q => Some(0x4),
-                Scancode::W => Some(0x5),
-                Scancode::E => Some(0x6),
-                Scancode::A => Some(0x7),
-                Scancode::S => Some(0x8),
-                Scancode::D => Some(0x9),
-                Scancode::Z => Some(0xA),
-                Scancode::C => Some(0xB),
-                Scancode::Num4 => Some(0xC),
-                Scancode::R => Some(0xD),
-                Scancode::F => Some(0xE),
-                Scancode::V => Some(0xF),
-                Scancode::Escape => return None,
-                _ => None,
-            };
-
-            if let Some(i) = code {
-                keys[i] = true;
+impl Frontend for GameCanvas<'_> {
+    fn read_keyboard_inputs(&mut self) -> Option<[bool; KEYBOARD_SIZE]> {
+        for event in self.event_pump.poll_iter() {
+            if let Some(gamepads) = self.gamepads.as_mut() {
+                gamepads.handle_event(&event);
+            }
+            // The hotkeys here and below are `keymap::RESERVED_KEYS`, which
+            // the keymap refuses to bind
+            match event {
+                Event::Quit { .. } => return None,
+                // M toggles the sound on and off
+                Event::KeyDown {
+                    scancode: Some(Scancode::M),
+                    repeat: false,
+                    ..
+                } => {
+                    if let Some(speaker) = self.speaker.as_mut() {
+                        speaker.toggle_mute();
+                    }
+                }
+                // F11 goes to and from fullscreen
+                Event::KeyDown {
+                    scancode: Some(Scancode::F11),
+                    repeat: false,
+                    ..
+                } => toggle_fullscreen(self.canvas.window_mut()),
+                // T switches to the next palette
+                Event::KeyDown {
+                    scancode: Some(Scancode::T),
+                    repeat: false,
+                    ..
+                } => {
+                    self.palettes.next();
+                    println!("Palette: {}", self.palettes.name());
+                }
+                // - and = change how many instructions run per frame
+                Event::KeyDown {
+                    scancode: Some(Scancode::Minus),
+                    ..
+                } => self.commands.push(Command::SlowDown),
+                Event::KeyDown {
+                    scancode: Some(Scancode::Equals),
+                    ..
+                } => self.commands.push(Command::SpeedUp),
+                // P pauses and continues, I steps one instruction and N steps
+                // over subroutine calls
+                Event::KeyDown {
+                    scancode: Some(Scancode::P),
+                    repeat: false,
+                    ..
+                } => self
+                    .commands
+                    .push(Command::Debug(DebugCommand::TogglePause)),
+                Event::KeyDown {
+                    scancode: Some(Scancode::I),
+                    ..
+                } => self.commands.push(Command::Debug(DebugCommand::Step)),
+                Event::KeyDown {
+                    scancode: Some(Scancode::N),
+                    ..
+                } => self.commands.push(Command::Debug(DebugCommand::StepOver)),
+                // F1-F9 save to that slot, holding shift loads from it instead
+                Event::KeyDown {
+                    scancode: Some(scancode),
+                    keymod,
+                    repeat: false,
+                    ..
+                } if slot_for(scancode).is_some() => {
+                    let slot = slot_for(scancode).unwrap();
+                    if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+                        self.commands.push(Command::LoadState(slot));
+                    } else {
+                        self.commands.push(Command::SaveState(slot));
+                    }
+                }
+                _ => (),
             }
         }
+        let state = self.event_pump.keyboard_state();
+        if state.is_scancode_pressed(Scancode::Escape) {
+            return None;
+        }
+        // Holding backspace runs time backwards, faster with shift
+        if state.is_scancode_pressed(Scancode::Backspace) {
+            let shift = state.is_scancode_pressed(Scancode::LShift)
+                || state.is_scancode_pressed(Scancode::RShift);
+            self.commands
+                .push(Command::Rewind(if shift { FAST_REWIND_FRAMES } else { 1 }));
+        }
 
+        let mut keys = [false; KEYBOARD_SIZE];
+        for &(scancode, key) in &self.keys {
+            if state.is_scancode_pressed(scancode) {
+                keys[key] = true;
+            }
+        }
+        if let Some(gamepads) = &self.gamepads {
+            gamepads.press(&mut keys);
+        }
         Some(keys)
     }
 
-    pub fn draw_frame(&mut self, display: &[[u8; DISPLAY_WIDTH]; DISPLAY_HEIGHT]) {
-        // Set the whole background to black
-        self.canvas.set_draw_color(Color::RGB(0, 0, 0));
+    fn take_commands(&mut self) -> Vec<Command> {
+        std::mem::take(&mut self.commands)
+    }
+
+    fn play_sound(&mut self, sound: Sound) {
+        if let Some(speaker) = self.speaker.as_mut() {
+            speaker.set_sound(sound);
+        }
+    }
+
+    fn report_error(&mut self, error: &Chip8Error) {
+        let _ = show_simple_message_box(
+            MessageBoxFlag::ERROR,
+            "CHIP-8 program crashed",
+            &error.to_string(),
+            self.canvas.window(),
+        );
+    }
+
+    fn draw_frame(&mut self, display: &Framebuffer) {
+        if let Err(e) = self.upload(display) {
+            println!("Unable to draw the screen: {}", e);
+            return;
+        }
+        let Ok(window) = self.canvas.output_size() else {
+            return;
+        };
+        let screen = (display.width() as u32, display.height() as u32);
+        let viewport = scaling::viewport(self.scaling, screen, window);
+
+        // Anything outside the viewport is a black border
+        self.canvas.set_draw_color(Color::BLACK);
         self.canvas.clear();
-        self.canvas.set_draw_color(Color::RGB(255, 255, 255));
-        for row in 0..DISPLAY_HEIGHT {
-            for col in 0..DISPLAY_WIDTH {
-                if display[row][col] == 1 {
-                    let _ = self.canvas.fill_rect(Rect::new(
-                        (col as u32 * PIXEL_SCALE) as i32,
-                        (row as u32 * PIXEL_SCALE) as i32,
-                        PIXEL_SCALE,
-                        PIXEL_SCALE,
-                    ));
-                }
-            }
+        if let Some(texture) = &self.screen {
+            let _ = self.canvas.copy(
+                texture,
+                None,
+                Rect::new(
+                    viewport.x as i32,
+                    viewport.y as i32,
+                    viewport.width,
+                    viewport.height,
+                ),
+            );
         }
         self.canvas.present();
     }
 }
+
+/// Looks up the scancode for each host key in `keymap`.
+pub fn key_bindings(keymap: &Keymap) -> Result<Vec<(Scancode, usize)>, String> {
+    keymap
+        .bindings()
+        .map(|(name, key)| {
+            Scancode::from_name(name)
+                .map(|scancode| (scancode, key))
+                .ok_or_else(|| format!("Unknown key '{}' bound to CHIP-8 key {:X}", name, key))
+        })
+        .collect()
+}
+
+fn toggle_fullscreen(window: &mut Window) {
+    let state = match window.fullscreen_state() {
+        FullscreenType::Off => FullscreenType::Desktop,
+        _ => FullscreenType::Off,
+    };
+    if let Err(e) = window.set_fullscreen(state) {
+        println!("Unable to change to or from fullscreen: {}", e);
+    }
+}
+
+// The save slot bound to a function key
+fn slot_for(scancode: Scancode) -> Option<u8> {
+    let slot = match scancode {
+        Scancode::F1 => 1,
+        Scancode::F2 => 2,
+        Scancode::F3 => 3,
+        Scancode::F4 => 4,
+        Scancode::F5 => 5,
+        Scancode::F6 => 6,
+        Scancode::F7 => 7,
+        Scancode::F8 => 8,
+        Scancode::F9 => 9,
+        _ => return None,
+    };
+    Some(slot)
+}
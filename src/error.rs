@@ -0,0 +1,96 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// Everything that can go wrong loading or running a program.
+///
+/// Faults raised while executing carry the address and opcode of the
+/// instruction that caused them.
+#[derive(Debug)]
+pub enum Chip8Error {
+    /// The ROM does not fit in memory above 0x200.
+    RomTooLarge {
+        size: usize,
+        capacity: usize,
+    },
+    Io(io::Error),
+    /// A subroutine call with every stack entry already in use.
+    StackOverflow {
+        pc: usize,
+        opcode: u16,
+    },
+    /// A return with no subroutine call to return from.
+    StackUnderflow {
+        pc: usize,
+        opcode: u16,
+    },
+    /// An instruction tried to read or write past the end of memory.
+    MemoryOutOfBounds {
+        pc: usize,
+        opcode: u16,
+        address: usize,
+    },
+    UnknownOpcode {
+        pc: usize,
+        opcode: u16,
+    },
+    /// The program counter left memory, usually after a bad jump.
+    PcOutOfRange {
+        pc: usize,
+    },
+    /// A save state file that is damaged or from an incompatible version.
+    InvalidSaveState(String),
+    /// A movie file that is damaged, or was recorded with a different ROM.
+    InvalidMovie(String),
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Chip8Error::RomTooLarge { size, capacity } => write!(
+                f,
+                "ROM is {} bytes but only {} bytes of memory are available",
+                size, capacity
+            ),
+            Chip8Error::Io(e) => write!(f, "{}", e),
+            Chip8Error::StackOverflow { pc, opcode } => {
+                write!(f, "Stack overflow at {:#05X} ({:04X})", pc, opcode)
+            }
+            Chip8Error::StackUnderflow { pc, opcode } => {
+                write!(f, "Stack underflow at {:#05X} ({:04X})", pc, opcode)
+            }
+            Chip8Error::MemoryOutOfBounds {
+                pc,
+                opcode,
+                address,
+            } => write!(
+                f,
+                "Memory access out of bounds at {:#05X} ({:04X}): address {:#X}",
+                pc, opcode, address
+            ),
+            Chip8Error::UnknownOpcode { pc, opcode } => {
+                write!(f, "Unknown opcode at {:#05X} ({:04X})", pc, opcode)
+            }
+            Chip8Error::PcOutOfRange { pc } => {
+                write!(f, "Program counter out of range: {:#X}", pc)
+            }
+            Chip8Error::InvalidSaveState(reason) => write!(f, "Invalid save state: {}", reason),
+            Chip8Error::InvalidMovie(reason) => write!(f, "Invalid movie: {}", reason),
+        }
+    }
+}
+
+impl Error for Chip8Error {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Chip8Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Chip8Error {
+    fn from(e: io::Error) -> Self {
+        Chip8Error::Io(e)
+    }
+}
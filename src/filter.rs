@@ -0,0 +1,204 @@
+/*
+    Flicker filters.
+
+    CHIP-8 programs move a sprite by XOR-drawing it off and then on again
+    somewhere else, so whichever frame the screen happens to show often has
+    sprites missing and games flicker. A `FilterStage` sits between the
+    framebuffer and the frontend and smooths that over, turning the pixel
+    values into colours without touching the machine's own display:
+
+    fade - pixels light up at once but take a few frames to go dark, like the
+        phosphor on an old screen
+    blend - each pixel is the average of its colours over the last few frames
+    xor - a pixel that goes dark is only shown dark once it has stayed that
+        way for a frame, which hides a sprite being erased and redrawn
+*/
+
+use crate::framebuffer::Framebuffer;
+use crate::palette::{Palette, Rgb};
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+
+/// Fraction of its brightness a fading pixel loses each frame.
+pub const DEFAULT_DECAY: f32 = 0.4;
+/// Frames averaged by the blend filter.
+pub const DEFAULT_BLEND_FRAMES: usize = 3;
+const MAX_BLEND_FRAMES: usize = 16;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FlickerFilter {
+    /// Show each frame as it is.
+    #[default]
+    Off,
+    /// Fade pixels out, losing this fraction of their brightness each frame.
+    Fade(f32),
+    /// Average this many frames.
+    Blend(usize),
+    /// Keep pixels lit for a frame after they're erased.
+    XorErase,
+}
+
+impl FromStr for FlickerFilter {
+    type Err = String;
+
+    /// Parses `off`, `xor`, `fade` or `blend`, the last two optionally with a
+    /// setting after a colon, such as `fade:0.25` or `blend:4`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, setting) = match s.split_once(':') {
+            Some((name, setting)) => (name, Some(setting)),
+            None => (s, None),
+        };
+        let invalid = || format!("Invalid setting '{}' for the {} filter", s, name);
+        match (name.to_ascii_lowercase().as_str(), setting) {
+            ("off", None) => Ok(FlickerFilter::Off),
+            ("xor", None) => Ok(FlickerFilter::XorErase),
+            ("fade", None) => Ok(FlickerFilter::Fade(DEFAULT_DECAY)),
+            ("fade", Some(decay)) => decay
+                .parse()
+                .ok()
+                .filter(|decay| *decay > 0.0 && *decay <= 1.0)
+                .map(FlickerFilter::Fade)
+                .ok_or_else(|| format!("{}, expected a decay above 0 and up to 1", invalid())),
+            ("blend", None) => Ok(FlickerFilter::Blend(DEFAULT_BLEND_FRAMES)),
+            ("blend", Some(frames)) => frames
+                .parse()
+                .ok()
+                .filter(|frames| (1..=MAX_BLEND_FRAMES).contains(frames))
+                .map(FlickerFilter::Blend)
+                .ok_or_else(|| {
+                    format!(
+                        "{}, expected between 1 and {} frames",
+                        invalid(),
+                        MAX_BLEND_FRAMES
+                    )
+                }),
+            ("off" | "xor", Some(_)) => Err(invalid()),
+            _ => Err(format!(
+                "Unknown filter '{}', expected off, fade, blend or xor",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for FlickerFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FlickerFilter::Off => write!(f, "off"),
+            FlickerFilter::Fade(decay) => write!(f, "fade:{}", decay),
+            FlickerFilter::Blend(frames) => write!(f, "blend:{}", frames),
+            FlickerFilter::XorErase => write!(f, "xor"),
+        }
+    }
+}
+
+/// Turns frames into colours through a `FlickerFilter`, remembering as much
+/// of the frames before as the filter needs.
+#[derive(Clone, Debug)]
+pub struct FilterStage {
+    filter: FlickerFilter,
+    // Width and height of the frames the history is for
+    size: (usize, usize),
+    // The colour each pixel is showing, while fading
+    glow: Vec<[f32; 3]>,
+    // Recent frames, newest last
+    history: VecDeque<Vec<u8>>,
+    output: Vec<Rgb>,
+}
+
+impl FilterStage {
+    pub fn new(filter: FlickerFilter) -> FilterStage {
+        FilterStage {
+            filter,
+            size: (0, 0),
+            glow: Vec::new(),
+            history: VecDeque::new(),
+            output: Vec::new(),
+        }
+    }
+
+    pub fn filter(&self) -> FlickerFilter {
+        self.filter
+    }
+
+    /// The colour of each pixel in `display`, row by row, after filtering.
+    pub fn apply(&mut self, display: &Framebuffer, palette: &Palette) -> &[Rgb] {
+        let size = (display.width(), display.height());
+        if size != self.size {
+            // Nothing from another resolution lines up with this one
+            self.size = size;
+            self.glow.clear();
+            self.history.clear();
+        }
+        let pixels = display.pixels();
+        self.output.clear();
+
+        match self.filter {
+            FlickerFilter::Off => {
+                self.output
+                    .extend(pixels.iter().map(|&pixel| palette.colour(pixel)));
+            }
+            FlickerFilter::Fade(decay) => {
+                let background = components(palette.colour(0));
+                if self.glow.len() != pixels.len() {
+                    self.glow = pixels
+                        .iter()
+                        .map(|&pixel| components(palette.colour(pixel)))
+                        .collect();
+                }
+                for (glow, &pixel) in self.glow.iter_mut().zip(pixels) {
+                    if pixel != 0 {
+                        *glow = components(palette.colour(pixel));
+                    } else {
+                        for (channel, background) in glow.iter_mut().zip(background) {
+                            *channel = background + (*channel - background) * (1.0 - decay);
+                        }
+                    }
+                }
+                self.output.extend(self.glow.iter().map(|&glow| rgb(glow)));
+            }
+            FlickerFilter::Blend(frames) => {
+                self.history.push_back(pixels.to_vec());
+                while self.history.len() > frames {
+                    self.history.pop_front();
+                }
+                let count = self.history.len() as f32;
+                self.output.extend((0..pixels.len()).map(|i| {
+                    let mut sum = [0.0; 3];
+                    for frame in &self.history {
+                        let colour = components(palette.colour(frame[i]));
+                        for (total, channel) in sum.iter_mut().zip(colour) {
+                            *total += channel;
+                        }
+                    }
+                    rgb(sum.map(|total| total / count))
+                }));
+            }
+            FlickerFilter::XorErase => {
+                let previous = self.history.pop_front();
+                self.output
+                    .extend(pixels.iter().enumerate().map(|(i, &pixel)| {
+                        let shown = match &previous {
+                            Some(previous) if pixel == 0 => previous[i],
+                            _ => pixel,
+                        };
+                        palette.colour(shown)
+                    }));
+                self.history.push_back(pixels.to_vec());
+            }
+        }
+        &self.output
+    }
+}
+
+fn components(Rgb(r, g, b): Rgb) -> [f32; 3] {
+    [r as f32, g as f32, b as f32]
+}
+
+fn rgb([r, g, b]: [f32; 3]) -> Rgb {
+    Rgb(r.round() as u8, g.round() as u8, b.round() as u8)
+}
+
+#[cfg(test)]
+mod tests;
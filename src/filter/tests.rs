@@ -0,0 +1,137 @@
+// One pixel is lit or cleared over a run of frames. With the classic palette it
+// is shown black, white or a grey in between, so its red channel is enough.
+
+use super::*;
+use crate::framebuffer::{HIRES_HEIGHT, HIRES_WIDTH, LORES_HEIGHT, LORES_WIDTH};
+
+// A low resolution frame with only the top left pixel possibly lit
+fn frame(lit: bool) -> Framebuffer {
+    let mut frame = Framebuffer::new(LORES_WIDTH, LORES_HEIGHT);
+    if lit {
+        frame.xor(0, 0, 1);
+    }
+    frame
+}
+
+// How bright the top left pixel is shown on each frame
+fn brightness(filter: FlickerFilter, frames: &[bool]) -> Vec<u8> {
+    let mut stage = FilterStage::new(filter);
+    let palette = Palette::default();
+    frames
+        .iter()
+        .map(|&lit| stage.apply(&frame(lit), &palette)[0].0)
+        .collect()
+}
+
+#[test]
+fn off_shows_each_frame_as_it_is() {
+    let frames = [true, false, true, false];
+    assert_eq!(brightness(FlickerFilter::Off, &frames), [255, 0, 255, 0]);
+}
+
+#[test]
+fn fade_lights_at_once_and_dims_slowly() {
+    let frames = [false, true, false, false, false, true];
+    assert_eq!(
+        brightness(FlickerFilter::Fade(DEFAULT_DECAY), &frames),
+        [0, 255, 153, 92, 55, 255]
+    );
+    assert_eq!(
+        brightness(FlickerFilter::Fade(1.0), &frames),
+        [0, 255, 0, 0, 0, 255]
+    );
+}
+
+#[test]
+fn blend_averages_the_last_frames() {
+    let frames = [true, false, false, true, true];
+    assert_eq!(
+        brightness(FlickerFilter::Blend(2), &frames),
+        [255, 128, 0, 128, 255]
+    );
+    // Until enough frames have gone by, it averages the ones there are
+    assert_eq!(
+        brightness(FlickerFilter::Blend(3), &frames),
+        [255, 128, 85, 85, 170]
+    );
+}
+
+#[test]
+fn xor_keeps_erased_pixels_for_a_frame() {
+    // Erased for one frame and redrawn, as when a sprite moves, never shows dark
+    assert_eq!(
+        brightness(FlickerFilter::XorErase, &[true, false, true]),
+        [255, 255, 255]
+    );
+    // Staying erased goes dark on the second frame
+    assert_eq!(
+        brightness(FlickerFilter::XorErase, &[true, false, false]),
+        [255, 255, 0]
+    );
+    assert_eq!(
+        brightness(FlickerFilter::XorErase, &[false, true, false]),
+        [0, 255, 255]
+    );
+}
+
+#[test]
+fn changing_resolution_forgets_earlier_frames() {
+    let palette = Palette::default();
+    let hires = Framebuffer::new(HIRES_WIDTH, HIRES_HEIGHT);
+    for filter in [
+        FlickerFilter::Fade(DEFAULT_DECAY),
+        FlickerFilter::Blend(DEFAULT_BLEND_FRAMES),
+        FlickerFilter::XorErase,
+    ] {
+        let mut stage = FilterStage::new(filter);
+        stage.apply(&frame(true), &palette);
+        let colours = stage.apply(&hires, &palette);
+        assert_eq!(colours.len(), HIRES_WIDTH * HIRES_HEIGHT);
+        assert_eq!(colours[0], palette.colour(0), "{}", filter);
+    }
+}
+
+#[test]
+fn parses_filters_and_their_settings() {
+    for (text, filter) in [
+        ("off", FlickerFilter::Off),
+        ("xor", FlickerFilter::XorErase),
+        ("fade", FlickerFilter::Fade(DEFAULT_DECAY)),
+        ("fade:0.25", FlickerFilter::Fade(0.25)),
+        ("fade:1", FlickerFilter::Fade(1.0)),
+        ("blend", FlickerFilter::Blend(DEFAULT_BLEND_FRAMES)),
+        ("blend:1", FlickerFilter::Blend(1)),
+        ("Blend:16", FlickerFilter::Blend(MAX_BLEND_FRAMES)),
+    ] {
+        assert_eq!(text.parse::<FlickerFilter>(), Ok(filter), "{}", text);
+        assert_eq!(filter.to_string().parse::<FlickerFilter>(), Ok(filter));
+    }
+}
+
+#[test]
+fn rejects_settings_out_of_range() {
+    let error = |text: &str| text.parse::<FlickerFilter>().unwrap_err();
+    for text in ["fade:0", "fade:-0.5", "fade:1.5", "fade:"] {
+        assert_eq!(
+            error(text),
+            format!(
+                "Invalid setting '{}' for the fade filter, expected a decay above 0 and up to 1",
+                text
+            )
+        );
+    }
+    for text in ["blend:0", "blend:17", "blend:two"] {
+        assert_eq!(
+            error(text),
+            format!(
+                "Invalid setting '{}' for the blend filter, expected between 1 and 16 frames",
+                text
+            )
+        );
+    }
+    assert_eq!(error("xor:1"), "Invalid setting 'xor:1' for the xor filter");
+    assert_eq!(
+        error("nope"),
+        "Unknown filter 'nope', expected off, fade, blend or xor"
+    );
+}
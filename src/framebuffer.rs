@@ -0,0 +1,157 @@
+/*
+    The emulated screen.
+
+    CHIP-8 only ever has a 64x32 display, but SUPER-CHIP programs can switch to a
+    128x64 high resolution mode at any time, so the buffer is sized at runtime.
+    Pixels are stored row by row, one byte per pixel.
+
+    XO-CHIP adds a second bitplane, so each pixel is a bitmask of the planes it is
+    lit on: 0 is off, 1 and 2 are lit on the first or second plane only, and 3 is
+    lit on both. Frontends pick one of four colours for each value. Plain CHIP-8
+    programs only ever draw to the first plane.
+*/
+
+pub const LORES_WIDTH: usize = 64;
+pub const LORES_HEIGHT: usize = 32;
+pub const HIRES_WIDTH: usize = 128;
+pub const HIRES_HEIGHT: usize = 64;
+pub const PLANE_COUNT: usize = 2;
+/// Mask selecting every plane.
+pub const ALL_PLANES: u8 = (1 << PLANE_COUNT) - 1;
+
+// How `to_text` draws each pixel value
+const PIXEL_CHARS: [char; 4] = ['.', '#', '+', '*'];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Default for Framebuffer {
+    fn default() -> Self {
+        Framebuffer::new(LORES_WIDTH, LORES_HEIGHT)
+    }
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Framebuffer {
+        Framebuffer {
+            width,
+            height,
+            pixels: vec![0; width * height],
+        }
+    }
+
+    /// Rebuilds a buffer from the values returned by `pixels`, or `None` if
+    /// the size is not one of the two resolutions, there are not exactly
+    /// `width * height` pixels or any of them is lit on planes that don't exist.
+    pub fn from_pixels(width: usize, height: usize, pixels: &[u8]) -> Option<Framebuffer> {
+        let size = (width, height);
+        if size != (LORES_WIDTH, LORES_HEIGHT) && size != (HIRES_WIDTH, HIRES_HEIGHT) {
+            return None;
+        }
+        if pixels.len() != width * height || pixels.iter().any(|&pixel| pixel > ALL_PLANES) {
+            return None;
+        }
+        Some(Framebuffer {
+            width,
+            height,
+            pixels: pixels.to_vec(),
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn is_hires(&self) -> bool {
+        self.width == HIRES_WIDTH
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        self.pixels[y * self.width + x]
+    }
+
+    /// All pixels, row by row.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[u8]> {
+        self.pixels.chunks(self.width)
+    }
+
+    /// The screen as text, one line per row. Unlit pixels are `.`, pixels lit
+    /// on the first plane `#`, on the second `+` and on both `*`.
+    pub fn to_text(&self) -> String {
+        let mut text = String::with_capacity((self.width + 1) * self.height);
+        for row in self.rows() {
+            text.extend(row.iter().map(|&pixel| PIXEL_CHARS[pixel as usize & 3]));
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Turns off every pixel on the planes in the `planes` mask.
+    pub fn clear(&mut self, planes: u8) {
+        for pixel in self.pixels.iter_mut() {
+            *pixel &= !planes;
+        }
+    }
+
+    /// Changes the resolution, which also clears the screen.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+        self.pixels = vec![0; width * height];
+    }
+
+    /// Flips the pixel at (x, y) on a single plane, returning true if it was
+    /// lit on that plane and is now off.
+    pub fn xor(&mut self, x: usize, y: usize, plane: u8) -> bool {
+        let pixel = &mut self.pixels[y * self.width + x];
+        *pixel ^= plane;
+        *pixel & plane == 0
+    }
+
+    // Moves the selected planes by (dx, dy), filling the uncovered area with
+    // blank pixels. Planes that are not selected stay where they are.
+    fn shift(&mut self, dx: isize, dy: isize, planes: u8) {
+        let (width, height) = (self.width as isize, self.height as isize);
+        let source = self.pixels.clone();
+        for y in 0..height {
+            for x in 0..width {
+                let (from_x, from_y) = (x - dx, y - dy);
+                let moved = if (0..width).contains(&from_x) && (0..height).contains(&from_y) {
+                    source[(from_y * width + from_x) as usize] & planes
+                } else {
+                    0
+                };
+                let pixel = &mut self.pixels[(y * width + x) as usize];
+                *pixel = (*pixel & !planes) | moved;
+            }
+        }
+    }
+
+    pub fn scroll_down(&mut self, n: usize, planes: u8) {
+        self.shift(0, n as isize, planes);
+    }
+
+    pub fn scroll_up(&mut self, n: usize, planes: u8) {
+        self.shift(0, -(n as isize), planes);
+    }
+
+    pub fn scroll_right(&mut self, n: usize, planes: u8) {
+        self.shift(n as isize, 0, planes);
+    }
+
+    pub fn scroll_left(&mut self, n: usize, planes: u8) {
+        self.shift(-(n as isize), 0, planes);
+    }
+}
@@ -0,0 +1,120 @@
+use crate::audio::Sound;
+use crate::debugger::DebugCommand;
+use crate::error::Chip8Error;
+use crate::{Framebuffer, KEYBOARD_SIZE};
+
+/// Requests from the user that are about the emulator rather than the
+/// emulated keypad, usually bound to hotkeys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Run twice as many instructions per frame.
+    SpeedUp,
+    /// Run half as many instructions per frame.
+    SlowDown,
+    /// Save the machine to a numbered slot next to the ROM.
+    SaveState(u8),
+    /// Restore the machine from a numbered slot.
+    LoadState(u8),
+    /// Go back this many frames. Sent every frame while the rewind key is
+    /// held, and the program stays paused for any frame that has one.
+    Rewind(u32),
+    /// Pause, step, set breakpoints and so on.
+    Debug(DebugCommand),
+}
+
+/// Where the machine is at, for frontends that show it alongside the screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Status {
+    pub pc: usize,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub cycles_per_frame: u32,
+    pub paused: bool,
+}
+
+/// Everything the core needs from the outside world while it is running.
+///
+/// The binary implements this on top of SDL and the terminal, but anything
+/// that can supply keypad state and accept frames can drive `Processor::run`.
+pub trait Frontend {
+    /// Returns which of the 16 keys are currently held down, or `None` if the
+    /// user has asked to quit.
+    fn read_keyboard_inputs(&mut self) -> Option<[bool; KEYBOARD_SIZE]>;
+
+    /// Returns the commands issued since the last call. Called once per frame,
+    /// right after `read_keyboard_inputs`.
+    fn take_commands(&mut self) -> Vec<Command> {
+        Vec::new()
+    }
+
+    /// Presents the current contents of the display buffer. The resolution can
+    /// change between frames when a SUPER-CHIP program switches modes.
+    fn draw_frame(&mut self, display: &Framebuffer);
+
+    /// Called once per frame, just before `draw_frame`, with the state of the
+    /// machine. Frontends without anywhere to show it can ignore it.
+    fn show_status(&mut self, _status: &Status) {}
+
+    /// Called once per frame with what the speaker should be playing.
+    /// Frontends without sound can ignore it.
+    fn play_sound(&mut self, _sound: Sound) {}
+
+    /// Called when the program faults, just before `Processor::run` returns
+    /// the error, so the frontend can tell the user what happened.
+    fn report_error(&mut self, _error: &Chip8Error) {}
+
+    /// Tells the user the outcome of a command, such as the new speed or
+    /// whether a state was saved. Printed to the terminal unless overridden.
+    fn show_message(&mut self, message: &str) {
+        println!("{}", message);
+    }
+}
+
+/// Wraps another frontend and quits once a set number of frames have been presented.
+pub struct FrameLimit<'a, F> {
+    frontend: &'a mut F,
+    remaining: u64,
+}
+
+impl<'a, F: Frontend> FrameLimit<'a, F> {
+    pub fn new(frontend: &'a mut F, frames: u64) -> Self {
+        FrameLimit {
+            frontend,
+            remaining: frames,
+        }
+    }
+}
+
+impl<F: Frontend> Frontend for FrameLimit<'_, F> {
+    fn read_keyboard_inputs(&mut self) -> Option<[bool; KEYBOARD_SIZE]> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.frontend.read_keyboard_inputs()
+    }
+
+    fn take_commands(&mut self) -> Vec<Command> {
+        self.frontend.take_commands()
+    }
+
+    fn draw_frame(&mut self, display: &Framebuffer) {
+        self.remaining = self.remaining.saturating_sub(1);
+        self.frontend.draw_frame(display);
+    }
+
+    fn show_status(&mut self, status: &Status) {
+        self.frontend.show_status(status);
+    }
+
+    fn play_sound(&mut self, sound: Sound) {
+        self.frontend.play_sound(sound);
+    }
+
+    fn report_error(&mut self, error: &Chip8Error) {
+        self.frontend.report_error(error);
+    }
+
+    fn show_message(&mut self, message: &str) {
+        self.frontend.show_message(message);
+    }
+}
@@ -0,0 +1,145 @@
+extern crate sdl2;
+use chip8_emulator::keymap::{ControllerLayout, Keymap, PLAYERS};
+use chip8_emulator::KEYBOARD_SIZE;
+use sdl2::controller::{Axis, Button, GameController};
+use sdl2::event::Event;
+use sdl2::{GameControllerSubsystem, Sdl};
+
+/// A controller input that can hold a key down.
+#[derive(Clone, Copy, Debug)]
+pub enum Input {
+    Button(Button),
+    /// A stick or trigger pushed past the deadzone in the positive or
+    /// negative direction.
+    Axis(Axis, bool),
+}
+
+/// A `ControllerLayout` with its inputs looked up.
+pub struct Bindings {
+    // Each player's inputs and the CHIP-8 keys they press
+    players: [Vec<(Input, usize)>; PLAYERS],
+    // How far from the centre an axis has to be to count
+    threshold: i16,
+}
+
+impl Bindings {
+    pub fn new(layout: &ControllerLayout) -> Result<Bindings, String> {
+        let player = |keymap: &Keymap| {
+            keymap
+                .bindings()
+                .map(|(name, key)| {
+                    input(name).map(|input| (input, key)).ok_or_else(|| {
+                        format!(
+                            "Unknown controller input '{}' bound to CHIP-8 key {:X}",
+                            name, key
+                        )
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(Bindings {
+            players: [player(&layout.players[0])?, player(&layout.players[1])?],
+            threshold: (layout.deadzone * i16::MAX as f32) as i16,
+        })
+    }
+}
+
+fn input(name: &str) -> Option<Input> {
+    let name = name.to_ascii_lowercase();
+    if let Some(axis) = name.strip_suffix('+') {
+        return Axis::from_string(axis).map(|axis| Input::Axis(axis, true));
+    }
+    if let Some(axis) = name.strip_suffix('-') {
+        return Axis::from_string(axis).map(|axis| Input::Axis(axis, false));
+    }
+    Button::from_string(&name).map(Input::Button)
+}
+
+/// The connected game controllers, one per player. Controllers plugged in
+/// while running take the first free player, and unplugging one frees it.
+pub struct Gamepads {
+    subsystem: GameControllerSubsystem,
+    players: [Option<GameController>; PLAYERS],
+    bindings: Bindings,
+}
+
+impl Gamepads {
+    pub fn new(sdl_context: &Sdl, bindings: Bindings) -> Result<Gamepads, String> {
+        // Controllers already connected show up as added events on the first poll
+        let subsystem = sdl_context.game_controller()?;
+        Ok(Gamepads {
+            subsystem,
+            players: [None, None],
+            bindings,
+        })
+    }
+
+    /// Opens and closes controllers as they come and go.
+    pub fn handle_event(&mut self, event: &Event) {
+        match *event {
+            Event::ControllerDeviceAdded { which, .. } => self.connect(which),
+            Event::ControllerDeviceRemoved { which, .. } => {
+                for (player, slot) in self.players.iter_mut().enumerate() {
+                    if slot.as_ref().is_some_and(|pad| pad.instance_id() == which) {
+                        let pad = slot.take().unwrap();
+                        println!(
+                            "Player {} controller disconnected: {}",
+                            player + 1,
+                            pad.name()
+                        );
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn connect(&mut self, joystick_index: u32) {
+        let pad = match self.subsystem.open(joystick_index) {
+            Ok(pad) => pad,
+            Err(e) => {
+                println!("Unable to open controller {}: {}", joystick_index, e);
+                return;
+            }
+        };
+        // Don't give a controller that is already playing a second player
+        let id = pad.instance_id();
+        if self
+            .players
+            .iter()
+            .flatten()
+            .any(|open| open.instance_id() == id)
+        {
+            return;
+        }
+        match self.players.iter().position(Option::is_none) {
+            Some(player) => {
+                println!("Player {} controller connected: {}", player + 1, pad.name());
+                self.players[player] = Some(pad);
+            }
+            None => println!(
+                "Ignoring {}, {} controllers are already playing",
+                pad.name(),
+                PLAYERS
+            ),
+        }
+    }
+
+    /// Presses the keys held on any controller.
+    pub fn press(&self, keys: &mut [bool; KEYBOARD_SIZE]) {
+        let threshold = self.bindings.threshold;
+        for (pad, bindings) in self.players.iter().zip(&self.bindings.players) {
+            let Some(pad) = pad else {
+                continue;
+            };
+            for &(input, key) in bindings {
+                let held = match input {
+                    Input::Button(button) => pad.button(button),
+                    Input::Axis(axis, true) => pad.axis(axis) > threshold,
+                    Input::Axis(axis, false) => pad.axis(axis) < -threshold,
+                };
+                keys[key] |= held;
+            }
+        }
+    }
+}
@@ -0,0 +1,154 @@
+/*
+    Running without a frontend.
+
+    A `Headless` run drives `Processor::run_frame` as fast as it can, holding
+    down keys from a `KeyScript` as each frame comes up, until a frame limit, a
+    breakpoint or the program exiting stops it. Afterwards `dump` describes the
+    screen and registers as text, which is what CI checks against.
+*/
+
+use crate::debugger::{self, Breakpoint};
+use crate::error::Chip8Error;
+use crate::processor::{Processor, KEYBOARD_SIZE};
+use std::fmt;
+use std::str::FromStr;
+
+/// Which keys are held on which frames.
+///
+/// Written as `frame:keys` entries separated by spaces, commas or new lines,
+/// where keys are the hex digits held from that frame until the next entry, or
+/// `-` for none. `#` starts a comment. For example `60:5 64:- 120:4a` presses 5
+/// for four frames at frame 60, then holds 4 and A from frame 120 on.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct KeyScript {
+    // Sorted by frame
+    entries: Vec<(u64, [bool; KEYBOARD_SIZE])>,
+}
+
+impl KeyScript {
+    /// The keys held during `frame`.
+    pub fn keys_at(&self, frame: u64) -> [bool; KEYBOARD_SIZE] {
+        let i = self.entries.partition_point(|&(start, _)| start <= frame);
+        match i {
+            0 => [false; KEYBOARD_SIZE],
+            _ => self.entries[i - 1].1,
+        }
+    }
+}
+
+impl FromStr for KeyScript {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut entries = Vec::new();
+        let words = s
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or(""))
+            .flat_map(|line| line.split(|c: char| c == ',' || c.is_whitespace()))
+            .filter(|word| !word.is_empty());
+        for word in words {
+            let invalid = || format!("Expected a key entry like 60:5a, got '{}'", word);
+            let (frame, held) = word.split_once(':').ok_or_else(invalid)?;
+            let frame = frame.parse().map_err(|_| invalid())?;
+            let mut keys = [false; KEYBOARD_SIZE];
+            if held != "-" {
+                for digit in held.chars() {
+                    let key = digit.to_digit(16).ok_or_else(invalid)?;
+                    keys[key as usize] = true;
+                }
+            }
+            entries.push((frame, keys));
+        }
+        // Later entries for the same frame win
+        entries.sort_by_key(|&(frame, _)| frame);
+        entries.dedup_by(|later, earlier| {
+            let same = later.0 == earlier.0;
+            if same {
+                earlier.1 = later.1;
+            }
+            same
+        });
+        Ok(KeyScript { entries })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    FrameLimit,
+    Breakpoint,
+    Exited,
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reason = match self {
+            StopReason::FrameLimit => "reached the frame limit",
+            StopReason::Breakpoint => "hit a breakpoint",
+            StopReason::Exited => "the program exited",
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+/// How a headless run ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stop {
+    /// Frames run, counting the one a breakpoint stopped partway through.
+    pub frames: u64,
+    pub reason: StopReason,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Headless {
+    pub keys: KeyScript,
+    /// Stop after this many frames. Without a limit the run only ends at a
+    /// breakpoint or when the program exits.
+    pub frames: Option<u64>,
+    /// Stop when this is hit, like a debugger breakpoint.
+    pub until: Option<Breakpoint>,
+}
+
+impl Headless {
+    /// Runs `processor` until one of the stopping points. A breakpoint leaves
+    /// it paused in the debugger, so `Debugger::resume` carries on from there.
+    pub fn run(&self, processor: &mut Processor) -> Result<Stop, Chip8Error> {
+        let until = self
+            .until
+            .map(|breakpoint| processor.debugger_mut().add_breakpoint(breakpoint));
+        let result = self.run_frames(processor);
+        if let Some(number) = until {
+            processor.debugger_mut().remove_breakpoint(number);
+        }
+        result
+    }
+
+    fn run_frames(&self, processor: &mut Processor) -> Result<Stop, Chip8Error> {
+        let mut frames = 0;
+        loop {
+            let reason = if processor.has_exited() {
+                StopReason::Exited
+            } else if self.frames.is_some_and(|limit| frames >= limit) {
+                StopReason::FrameLimit
+            } else {
+                processor.set_keys(self.keys.keys_at(frames));
+                processor.run_frame()?;
+                frames += 1;
+                if !processor.debugger().is_paused() {
+                    continue;
+                }
+                processor.debugger_mut().take_report();
+                StopReason::Breakpoint
+            };
+            return Ok(Stop { frames, reason });
+        }
+    }
+}
+
+/// The screen followed by the registers, timers and next instruction.
+pub fn dump(processor: &Processor) -> String {
+    format!(
+        "{}\n{}\n",
+        processor.display().to_text(),
+        debugger::registers(processor)
+    )
+}
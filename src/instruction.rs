@@ -0,0 +1,284 @@
+/*
+    Decoding opcodes.
+
+    This is the one table of what each opcode means. The processor executes the
+    decoded `Instruction`, the disassembler prints it and the assembler encodes
+    it, so none of them can disagree about what a ROM contains. Variants are
+    named after the mnemonics in Cowgod's technical reference, and carry the
+    same fields:
+
+    nnn or addr - A 12-bit value, the lowest 12 bits of the instruction
+    n or nibble - A 4-bit value, the lowest 4 bits of the instruction
+    x - A 4-bit value, the lower 4 bits of the high byte of the instruction
+    y - A 4-bit value, the upper 4 bits of the low byte of the instruction
+    kk or byte - An 8-bit value, the lowest 8 bits of the instruction
+*/
+
+use crate::quirks::Platform;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    /// 0nnn - SYS addr
+    Sys(usize),
+    /// 00E0 - CLS
+    Cls,
+    /// 00EE - RET
+    Ret,
+    /// 00Cn - SCD nibble
+    Scd(usize),
+    /// 00Dn - SCU nibble
+    Scu(usize),
+    /// 00FB - SCR
+    Scr,
+    /// 00FC - SCL
+    Scl,
+    /// 00FD - EXIT
+    Exit,
+    /// 00FE - LOW
+    Low,
+    /// 00FF - HIGH
+    High,
+    /// 1nnn - JP addr
+    Jp(usize),
+    /// 2nnn - CALL addr
+    Call(usize),
+    /// 3xkk - SE Vx, byte
+    SeByte(usize, u8),
+    /// 4xkk - SNE Vx, byte
+    SneByte(usize, u8),
+    /// 5xy0 - SE Vx, Vy
+    SeReg(usize, usize),
+    /// 5xy2 - SAVE Vx - Vy
+    Save(usize, usize),
+    /// 5xy3 - LOAD Vx - Vy
+    Load(usize, usize),
+    /// 6xkk - LD Vx, byte
+    LdByte(usize, u8),
+    /// 7xkk - ADD Vx, byte
+    AddByte(usize, u8),
+    /// 8xy0 - LD Vx, Vy
+    LdReg(usize, usize),
+    /// 8xy1 - OR Vx, Vy
+    Or(usize, usize),
+    /// 8xy2 - AND Vx, Vy
+    And(usize, usize),
+    /// 8xy3 - XOR Vx, Vy
+    Xor(usize, usize),
+    /// 8xy4 - ADD Vx, Vy
+    AddReg(usize, usize),
+    /// 8xy5 - SUB Vx, Vy
+    Sub(usize, usize),
+    /// 8xy6 - SHR Vx {, Vy}
+    Shr(usize, usize),
+    /// 8xy7 - SUBN Vx, Vy
+    Subn(usize, usize),
+    /// 8xyE - SHL Vx {, Vy}
+    Shl(usize, usize),
+    /// 9xy0 - SNE Vx, Vy
+    SneReg(usize, usize),
+    /// Annn - LD I, addr
+    LdI(usize),
+    /// Bnnn - JP V0, addr. x is kept for the jump_uses_vx quirk.
+    JpV0(usize, usize),
+    /// Cxkk - RND Vx, byte
+    Rnd(usize, u8),
+    /// Dxyn - DRW Vx, Vy, nibble
+    Drw(usize, usize, usize),
+    /// Ex9E - SKP Vx
+    Skp(usize),
+    /// ExA1 - SKNP Vx
+    Sknp(usize),
+    /// F000 nnnn - LD I, long addr. The address is the following word.
+    LdILong,
+    /// Fn01 - PLANE n
+    Plane(usize),
+    /// F002 - AUDIO
+    Audio,
+    /// Fx07 - LD Vx, DT
+    LdVxDt(usize),
+    /// Fx0A - LD Vx, K
+    LdVxK(usize),
+    /// Fx15 - LD DT, Vx
+    LdDtVx(usize),
+    /// Fx18 - LD ST, Vx
+    LdStVx(usize),
+    /// Fx1E - ADD I, Vx
+    AddIVx(usize),
+    /// Fx29 - LD F, Vx
+    LdF(usize),
+    /// Fx30 - LD HF, Vx
+    LdHf(usize),
+    /// Fx33 - LD B, Vx
+    LdB(usize),
+    /// Fx3A - PITCH Vx
+    Pitch(usize),
+    /// Fx55 - LD [I], Vx
+    LdIVx(usize),
+    /// Fx65 - LD Vx, [I]
+    LdVxI(usize),
+    /// Fx75 - LD R, Vx
+    LdRVx(usize),
+    /// Fx85 - LD Vx, R
+    LdVxR(usize),
+}
+
+impl Instruction {
+    /// Decodes an opcode as `platform` would, or returns `None` if it is not
+    /// an instruction there. SUPER-CHIP's 00Cn and friends are plain SYS calls
+    /// on platforms that don't have them.
+    pub fn decode(opcode: u16, platform: Platform) -> Option<Instruction> {
+        // Split the opcode into nibbles (4bits)
+        let nibbles = (
+            (opcode & 0xF000) >> 12,
+            (opcode & 0x0F00) >> 8,
+            (opcode & 0x00F0) >> 4,
+            opcode & 0x000F,
+        );
+
+        let nnn = (opcode & 0x0FFF) as usize;
+        let n = nibbles.3 as usize;
+        let x = nibbles.1 as usize;
+        let y = nibbles.2 as usize;
+        let kk = (opcode & 0x00FF) as u8;
+
+        let schip = platform.has_schip_instructions();
+        let xo = platform == Platform::XoChip;
+
+        let instruction = match nibbles {
+            (0x0, 0x0, 0xE, 0xE) => Instruction::Ret,
+            (0x0, 0x0, 0xE, 0x0) => Instruction::Cls,
+            (0x0, 0x0, 0xC, _) if schip => Instruction::Scd(n),
+            (0x0, 0x0, 0xD, _) if xo => Instruction::Scu(n),
+            (0x0, 0x0, 0xF, 0xB) if schip => Instruction::Scr,
+            (0x0, 0x0, 0xF, 0xC) if schip => Instruction::Scl,
+            (0x0, 0x0, 0xF, 0xD) if schip => Instruction::Exit,
+            (0x0, 0x0, 0xF, 0xE) if schip => Instruction::Low,
+            (0x0, 0x0, 0xF, 0xF) if schip => Instruction::High,
+            (0x0, _, _, _) => Instruction::Sys(nnn),
+            (0x1, _, _, _) => Instruction::Jp(nnn),
+            (0x2, _, _, _) => Instruction::Call(nnn),
+            (0x3, _, _, _) => Instruction::SeByte(x, kk),
+            (0x4, _, _, _) => Instruction::SneByte(x, kk),
+            (0x5, _, _, 0x0) => Instruction::SeReg(x, y),
+            (0x5, _, _, 0x2) if xo => Instruction::Save(x, y),
+            (0x5, _, _, 0x3) if xo => Instruction::Load(x, y),
+            (0x6, _, _, _) => Instruction::LdByte(x, kk),
+            (0x7, _, _, _) => Instruction::AddByte(x, kk),
+            (0x8, _, _, 0x0) => Instruction::LdReg(x, y),
+            (0x8, _, _, 0x1) => Instruction::Or(x, y),
+            (0x8, _, _, 0x2) => Instruction::And(x, y),
+            (0x8, _, _, 0x3) => Instruction::Xor(x, y),
+            (0x8, _, _, 0x4) => Instruction::AddReg(x, y),
+            (0x8, _, _, 0x5) => Instruction::Sub(x, y),
+            (0x8, _, _, 0x6) => Instruction::Shr(x, y),
+            (0x8, _, _, 0x7) => Instruction::Subn(x, y),
+            (0x8, _, _, 0xE) => Instruction::Shl(x, y),
+            (0x9, _, _, 0x0) => Instruction::SneReg(x, y),
+            (0xA, _, _, _) => Instruction::LdI(nnn),
+            (0xB, _, _, _) => Instruction::JpV0(x, nnn),
+            (0xC, _, _, _) => Instruction::Rnd(x, kk),
+            (0xD, _, _, _) => Instruction::Drw(x, y, n),
+            (0xE, _, 0x9, 0xE) => Instruction::Skp(x),
+            (0xE, _, 0xA, 0x1) => Instruction::Sknp(x),
+            (0xF, 0x0, 0x0, 0x0) if xo => Instruction::LdILong,
+            (0xF, _, 0x0, 0x1) if xo => Instruction::Plane(x),
+            (0xF, 0x0, 0x0, 0x2) if xo => Instruction::Audio,
+            (0xF, _, 0x0, 0x7) => Instruction::LdVxDt(x),
+            (0xF, _, 0x0, 0xA) => Instruction::LdVxK(x),
+            (0xF, _, 0x1, 0x5) => Instruction::LdDtVx(x),
+            (0xF, _, 0x1, 0x8) => Instruction::LdStVx(x),
+            (0xF, _, 0x1, 0xE) => Instruction::AddIVx(x),
+            (0xF, _, 0x2, 0x9) => Instruction::LdF(x),
+            (0xF, _, 0x3, 0x0) if schip => Instruction::LdHf(x),
+            (0xF, _, 0x3, 0x3) => Instruction::LdB(x),
+            (0xF, _, 0x3, 0xA) if xo => Instruction::Pitch(x),
+            (0xF, _, 0x5, 0x5) => Instruction::LdIVx(x),
+            (0xF, _, 0x6, 0x5) => Instruction::LdVxI(x),
+            (0xF, _, 0x7, 0x5) if schip => Instruction::LdRVx(x),
+            (0xF, _, 0x8, 0x5) if schip => Instruction::LdVxR(x),
+            _ => return None,
+        };
+        Some(instruction)
+    }
+
+    /// The opcode for this instruction, the reverse of `decode`. `LdILong`
+    /// gives the F000 half, the address has to be written after it.
+    pub fn encode(&self) -> u16 {
+        let xy = |op: u16, x: usize, y: usize, n: u16| op | (x as u16) << 8 | (y as u16) << 4 | n;
+        let xkk = |op: u16, x: usize, kk: u8| op | (x as u16) << 8 | kk as u16;
+        match *self {
+            Instruction::Sys(nnn) => nnn as u16,
+            Instruction::Cls => 0x00E0,
+            Instruction::Ret => 0x00EE,
+            Instruction::Scd(n) => 0x00C0 | n as u16,
+            Instruction::Scu(n) => 0x00D0 | n as u16,
+            Instruction::Scr => 0x00FB,
+            Instruction::Scl => 0x00FC,
+            Instruction::Exit => 0x00FD,
+            Instruction::Low => 0x00FE,
+            Instruction::High => 0x00FF,
+            Instruction::Jp(nnn) => 0x1000 | nnn as u16,
+            Instruction::Call(nnn) => 0x2000 | nnn as u16,
+            Instruction::SeByte(x, kk) => xkk(0x3000, x, kk),
+            Instruction::SneByte(x, kk) => xkk(0x4000, x, kk),
+            Instruction::SeReg(x, y) => xy(0x5000, x, y, 0x0),
+            Instruction::Save(x, y) => xy(0x5000, x, y, 0x2),
+            Instruction::Load(x, y) => xy(0x5000, x, y, 0x3),
+            Instruction::LdByte(x, kk) => xkk(0x6000, x, kk),
+            Instruction::AddByte(x, kk) => xkk(0x7000, x, kk),
+            Instruction::LdReg(x, y) => xy(0x8000, x, y, 0x0),
+            Instruction::Or(x, y) => xy(0x8000, x, y, 0x1),
+            Instruction::And(x, y) => xy(0x8000, x, y, 0x2),
+            Instruction::Xor(x, y) => xy(0x8000, x, y, 0x3),
+            Instruction::AddReg(x, y) => xy(0x8000, x, y, 0x4),
+            Instruction::Sub(x, y) => xy(0x8000, x, y, 0x5),
+            Instruction::Shr(x, y) => xy(0x8000, x, y, 0x6),
+            Instruction::Subn(x, y) => xy(0x8000, x, y, 0x7),
+            Instruction::Shl(x, y) => xy(0x8000, x, y, 0xE),
+            Instruction::SneReg(x, y) => xy(0x9000, x, y, 0x0),
+            Instruction::LdI(nnn) => 0xA000 | nnn as u16,
+            Instruction::JpV0(_, nnn) => 0xB000 | nnn as u16,
+            Instruction::Rnd(x, kk) => xkk(0xC000, x, kk),
+            Instruction::Drw(x, y, n) => xy(0xD000, x, y, n as u16),
+            Instruction::Skp(x) => xkk(0xE000, x, 0x9E),
+            Instruction::Sknp(x) => xkk(0xE000, x, 0xA1),
+            Instruction::LdILong => 0xF000,
+            Instruction::Plane(n) => xkk(0xF000, n, 0x01),
+            Instruction::Audio => 0xF002,
+            Instruction::LdVxDt(x) => xkk(0xF000, x, 0x07),
+            Instruction::LdVxK(x) => xkk(0xF000, x, 0x0A),
+            Instruction::LdDtVx(x) => xkk(0xF000, x, 0x15),
+            Instruction::LdStVx(x) => xkk(0xF000, x, 0x18),
+            Instruction::AddIVx(x) => xkk(0xF000, x, 0x1E),
+            Instruction::LdF(x) => xkk(0xF000, x, 0x29),
+            Instruction::LdHf(x) => xkk(0xF000, x, 0x30),
+            Instruction::LdB(x) => xkk(0xF000, x, 0x33),
+            Instruction::Pitch(x) => xkk(0xF000, x, 0x3A),
+            Instruction::LdIVx(x) => xkk(0xF000, x, 0x55),
+            Instruction::LdVxI(x) => xkk(0xF000, x, 0x65),
+            Instruction::LdRVx(x) => xkk(0xF000, x, 0x75),
+            Instruction::LdVxR(x) => xkk(0xF000, x, 0x85),
+        }
+    }
+
+    /// Length in bytes. Everything is 2 bytes except XO-CHIP's `F000 nnnn`.
+    pub fn size(&self) -> usize {
+        match self {
+            Instruction::LdILong => 4,
+            _ => 2,
+        }
+    }
+
+    /// True for the instructions that may skip over the one after them.
+    pub fn is_skip(&self) -> bool {
+        matches!(
+            self,
+            Instruction::SeByte(..)
+                | Instruction::SneByte(..)
+                | Instruction::SeReg(..)
+                | Instruction::SneReg(..)
+                | Instruction::Skp(_)
+                | Instruction::Sknp(_)
+        )
+    }
+}
@@ -0,0 +1,217 @@
+/*
+    Which host keys and controller buttons press which CHIP-8 keys.
+
+    Host keys are named the way SDL names scancodes, such as `Q`, `1`, `Up` or
+    `Keypad 8`, and scancodes follow where a key sits on the keyboard rather
+    than what is printed on it. Controller inputs use SDL's names from
+    controller mappings: buttons such as `a`, `start` or `dpup`, and stick
+    directions written as an axis and a sign, such as `leftx-` for the left
+    stick pushed left or `lefty+` for it pushed down. Each CHIP-8 key can have
+    any number of inputs, and an input presses at most one CHIP-8 key.
+
+    The frontends keep a few host keys for themselves, listed in
+    `RESERVED_KEYS`, and those can't be bound.
+*/
+
+use crate::KEYBOARD_SIZE;
+use toml::{Table, Value};
+
+/// Controllers that can play at once, for two player games.
+pub const PLAYERS: usize = 2;
+/// Fraction of a stick's travel from the centre that is ignored.
+pub const DEFAULT_DEADZONE: f32 = 0.25;
+
+/// The classic layout, the left hand side of a QWERTY keyboard:
+///
+/// ```text
+///     1 2 3 C           1 2 3 4
+///     4 5 6 D  --->     Q W E R
+///     7 8 9 E           A S D F
+///     A 0 B F           Z X C V
+/// ```
+const DEFAULT_KEYS: [&str; KEYBOARD_SIZE] = [
+    "X", "1", "2", "3", "Q", "W", "E", "A", "S", "D", "Z", "C", "4", "R", "F", "V",
+];
+
+/// Host keys the frontends use for muting, fullscreen, palettes, speed,
+/// debugging, save states, rewinding and quitting, and the shift keys that
+/// change what some of those do. `+` is how terminals see shift and `=`.
+pub const RESERVED_KEYS: [&str; 22] = [
+    "M",
+    "F11",
+    "T",
+    "P",
+    "I",
+    "N",
+    "-",
+    "=",
+    "+",
+    "Backspace",
+    "Left Shift",
+    "Right Shift",
+    "Escape",
+    "F1",
+    "F2",
+    "F3",
+    "F4",
+    "F5",
+    "F6",
+    "F7",
+    "F8",
+    "F9",
+];
+
+/// The d-pad and left stick press 2, 4, 6 and 8, the directions most games
+/// use, and the face buttons press 5, 0, 1 and 3.
+const DEFAULT_BUTTONS: [&[&str]; KEYBOARD_SIZE] = [
+    &["b"],
+    &["x"],
+    &["dpup", "lefty-"],
+    &["y"],
+    &["dpleft", "leftx-"],
+    &["a"],
+    &["dpright", "leftx+"],
+    &[],
+    &["dpdown", "lefty+"],
+    &[],
+    &[],
+    &[],
+    &[],
+    &[],
+    &[],
+    &[],
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Keymap {
+    // Input names for each CHIP-8 key
+    keys: [Vec<String>; KEYBOARD_SIZE],
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap {
+            keys: DEFAULT_KEYS.map(|name| vec![name.to_string()]),
+        }
+    }
+}
+
+impl Keymap {
+    /// The default layout for a game controller.
+    pub fn controller() -> Keymap {
+        Keymap {
+            keys: DEFAULT_BUTTONS.map(|names| names.iter().map(|name| name.to_string()).collect()),
+        }
+    }
+
+    /// The host keys bound to a CHIP-8 key.
+    pub fn host_keys(&self, key: usize) -> &[String] {
+        &self.keys[key]
+    }
+
+    /// Every binding as a host key name and the CHIP-8 key it presses.
+    pub fn bindings(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.keys
+            .iter()
+            .enumerate()
+            .flat_map(|(key, names)| names.iter().map(move |name| (name.as_str(), key)))
+    }
+
+    /// Binds `host_keys` to `key` in place of whatever it had. The host keys
+    /// stop pressing any other key they were bound to.
+    pub fn bind(&mut self, key: usize, host_keys: Vec<String>) {
+        for names in self.keys.iter_mut() {
+            names.retain(|name| !host_keys.iter().any(|new| new.eq_ignore_ascii_case(name)));
+        }
+        self.keys[key] = host_keys;
+    }
+
+    /// Fails if a host key in `RESERVED_KEYS` is bound, which would press a
+    /// CHIP-8 key and do something else at the same time.
+    pub fn check_reserved(&self) -> Result<(), String> {
+        let reserved = |name: &str| RESERVED_KEYS.iter().any(|r| r.eq_ignore_ascii_case(name));
+        match self.bindings().find(|&(name, _)| reserved(name)) {
+            Some((name, key)) => Err(format!(
+                "Can't bind {} to key {:X}, the emulator keeps it for itself",
+                name, key
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Applies a `[keys]` table from the config file, where each entry binds a
+    /// CHIP-8 key (a hex digit) to an input name or a list of them. Keys the
+    /// table leaves out keep their bindings.
+    pub fn apply(&mut self, table: &Table) -> Result<(), String> {
+        for (digit, value) in table {
+            let key = u8::from_str_radix(digit, 16)
+                .ok()
+                .filter(|_| digit.len() == 1)
+                .ok_or_else(|| format!("Expected a CHIP-8 key from 0 to F, got '{}'", digit))?;
+            let invalid = || format!("Expected an input name or a list of them for key {}", digit);
+            let host_keys = match value {
+                Value::String(name) => vec![name.clone()],
+                Value::Array(names) => names
+                    .iter()
+                    .map(|name| name.as_str().map(str::to_string).ok_or_else(invalid))
+                    .collect::<Result<_, _>>()?,
+                _ => return Err(invalid()),
+            };
+            self.bind(key as usize, host_keys);
+        }
+        Ok(())
+    }
+}
+
+/// How game controllers press keys. The first controller connected plays as
+/// the first player and the second as the second, each with its own keymap.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ControllerLayout {
+    pub players: [Keymap; PLAYERS],
+    /// Fraction of a stick's travel from the centre that is ignored, so a
+    /// stick resting slightly off centre doesn't hold a key down.
+    pub deadzone: f32,
+}
+
+impl Default for ControllerLayout {
+    fn default() -> Self {
+        ControllerLayout {
+            players: [Keymap::controller(), Keymap::controller()],
+            deadzone: DEFAULT_DEADZONE,
+        }
+    }
+}
+
+impl ControllerLayout {
+    /// Applies a `[controller]` table from the config file. `deadzone` sets the
+    /// deadzone, and the tables `1` and `2` hold each player's bindings in the
+    /// same form as `[keys]`.
+    pub fn apply(&mut self, table: &Table) -> Result<(), String> {
+        for (name, value) in table {
+            if name == "deadzone" {
+                self.deadzone = value
+                    .as_float()
+                    .or_else(|| value.as_integer().map(|n| n as f64))
+                    .filter(|deadzone| (0.0..1.0).contains(deadzone))
+                    .ok_or("Expected a deadzone from 0 to just under 1")?
+                    as f32;
+                continue;
+            }
+            let player = name
+                .parse::<usize>()
+                .ok()
+                .filter(|player| (1..=PLAYERS).contains(player))
+                .ok_or_else(|| {
+                    format!(
+                        "Expected deadzone or a player from 1 to {} in [controller], got '{}'",
+                        PLAYERS, name
+                    )
+                })?;
+            let bindings = value
+                .as_table()
+                .ok_or_else(|| format!("Expected [controller.{}] to be a table", player))?;
+            self.players[player - 1].apply(bindings)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,42 @@
+/*
+    Headless CHIP-8 core.
+
+    The interpreter itself lives in `processor` and knows nothing about windows,
+    keyboards or speakers. Anything that wants to show the emulator to a user
+    implements the `Frontend` trait and hands it to `Processor::run`, while tools
+    and tests can drive the machine directly with `step` and `run_frame`, or
+    with a scripted `headless::Headless` run.
+*/
+
+pub mod asm;
+pub mod audio;
+pub mod config;
+pub mod debugger;
+pub mod disasm;
+pub mod error;
+pub mod filter;
+pub mod framebuffer;
+pub mod frontend;
+pub mod headless;
+pub mod instruction;
+pub mod keymap;
+pub mod movie;
+pub mod palette;
+pub mod processor;
+pub mod quirks;
+pub mod rewind;
+pub mod rng;
+pub mod scaling;
+pub mod scheduler;
+pub mod snapshot;
+pub mod textmode;
+
+pub use error::Chip8Error;
+pub use framebuffer::Framebuffer;
+pub use frontend::{Command, Frontend};
+pub use processor::{Processor, KEYBOARD_SIZE};
+pub use quirks::{Platform, Quirks};
+pub use snapshot::Snapshot;
+
+/// The emulated machine, under the name embedders usually look for.
+pub type Chip8 = Processor;
@@ -1,16 +1,215 @@
-mod processor;
-use processor::*;
+mod cli;
+#[cfg(feature = "sdl")]
 mod display;
+#[cfg(feature = "sdl")]
+mod gamepads;
+#[cfg(feature = "sdl")]
+mod prompt;
+#[cfg(feature = "sdl")]
+mod speaker;
+#[cfg(feature = "terminal")]
+mod terminal;
+use chip8_emulator::asm::SourceMap;
+use chip8_emulator::debugger::DebugCommand;
+use chip8_emulator::frontend::FrameLimit;
+use chip8_emulator::headless::{self, Headless};
+use chip8_emulator::movie::Movie;
+use chip8_emulator::{Chip8Error, Frontend, Processor};
+use cli::{Action, Options};
 use std::env;
+use std::process;
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() < 2 {
-        println!("Usage: cargo run <rom>");
-        return;
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = match cli::parse(&args) {
+        Ok(Action::Run(options)) => *options,
+        Ok(Action::ListRoms) => {
+            match cli::bundled_roms() {
+                Ok(roms) => roms.iter().for_each(|rom| println!("{}", rom)),
+                Err(e) => exit_with(format!("Unable to list the bundled roms: {}", e)),
+            }
+            return;
+        }
+        Ok(Action::Help) => {
+            println!("{}", cli::USAGE);
+            return;
+        }
+        Err(e) => exit_with(format!("{}\nRun with --help for usage.", e)),
+    };
+
+    let mut processor = Processor::with_platform(options.platform, options.quirks);
+    processor.set_cycles_per_frame(options.cycles_per_frame);
+    processor.set_rewind_memory(options.rewind_memory);
+    if let Some(seed) = options.seed {
+        processor.seed_rng(seed);
+    }
+    if let Err(e) = processor.load_rom(&options.rom.to_string_lossy()) {
+        exit_with(format!("Unable to load {}: {}", options.rom.display(), e));
+    }
+
+    // ROMs built with chip8-asm have a source map alongside them
+    let map = options.rom.with_extension("map");
+    if map.is_file() {
+        match SourceMap::load(&map) {
+            Ok(source_map) => processor.debugger_mut().set_source_map(source_map),
+            Err(e) => eprintln!("Ignoring source map {}: {}", map.display(), e),
+        }
+    }
+
+    // Playing a movie until it runs out is enough of a stopping point
+    let mut frames = options.frames;
+    if let Some(path) = &options.play {
+        let movie = Movie::load(path)
+            .unwrap_or_else(|e| exit_with(format!("Unable to load {}: {}", path.display(), e)));
+        if options.until.is_none() {
+            frames = frames.or(Some(movie.len() as u64));
+        }
+        if let Err(e) = processor.play_movie(movie) {
+            exit_with(format!("Unable to play {}: {}", path.display(), e));
+        }
+    }
+    if options.record.is_some() {
+        processor.record_movie();
+    }
+
+    if options.debug {
+        println!("Paused, type help for debugger commands");
+        println!(
+            "Random seed {}, pass --seed to repeat this run",
+            processor.seed()
+        );
+        println!("{}", processor.debug(DebugCommand::Pause));
+    }
+
+    let result = if options.headless {
+        run_headless(&mut processor, &options, frames)
+    } else if options.terminal {
+        run_terminal(&mut processor, &options)
+    } else {
+        run_windowed(&mut processor, &options)
+    };
+    // Keep the recording even if the program crashed, it shows how to get there
+    let recording = options
+        .record
+        .as_ref()
+        .filter(|_| processor.is_recording_movie());
+    if let Some(path) = recording {
+        let movie = processor.stop_movie().expect("a movie is recording");
+        match movie.save(path) {
+            Ok(()) => println!("Recorded {} frames to {}", movie.len(), path.display()),
+            Err(e) => eprintln!("Unable to save {}: {}", path.display(), e),
+        }
+    }
+    if let Err(e) = result {
+        exit_with(e.to_string());
+    }
+    // Windowed playback shows desyncs as they happen, headless runs fail on them
+    if let Some(frame) = processor.movie_desync().filter(|_| options.headless) {
+        exit_with(format!(
+            "Movie desynced: the state after frame {} differs from the recording",
+            frame
+        ));
+    }
+}
+
+fn exit_with(message: String) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+// Runs as fast as possible with no window, then prints the screen and registers
+fn run_headless(
+    processor: &mut Processor,
+    options: &Options,
+    frames: Option<u64>,
+) -> Result<(), Chip8Error> {
+    let run = Headless {
+        keys: options.keys.clone(),
+        frames,
+        until: options.until,
+    };
+    // Show where things stood even if the program crashed
+    let result = run.run(processor);
+    print!("{}", headless::dump(processor));
+    let stop = result?;
+    println!(
+        "Stopped after {} frames, {} (seed {})",
+        stop.frames,
+        stop.reason,
+        processor.seed()
+    );
+    Ok(())
+}
+
+#[cfg(feature = "sdl")]
+fn run_windowed(processor: &mut Processor, options: &Options) -> Result<(), Chip8Error> {
+    let title = options
+        .rom
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let keys = display::key_bindings(&options.keymap).unwrap_or_else(|e| exit_with(e));
+    let pad_bindings =
+        gamepads::Bindings::new(&options.controllers).unwrap_or_else(|e| exit_with(e));
+    let settings = display::WindowSettings {
+        title,
+        pixel_scale: options.scale,
+        scaling: options.scaling,
+        fullscreen: options.fullscreen,
+        filter: options.filter,
+    };
+    let video = display::Video::open(&settings);
+    let texture_creator = video.texture_creator();
+    let mut game_canvas = display::GameCanvas::new(
+        video,
+        &texture_creator,
+        settings,
+        options.palettes.clone(),
+        options.audio,
+        keys,
+        pad_bindings,
+    );
+    if options.debug {
+        let mut prompt = prompt::DebugPrompt::new(&mut game_canvas);
+        run_limited(processor, &mut prompt, options.frames)
+    } else {
+        run_limited(processor, &mut game_canvas, options.frames)
     }
+}
 
-    let mut processor = Processor::new(&args[1]);
-    processor.load_rom(&format!("roms/{}", &args[1]));
-    processor.start();
+#[cfg(not(feature = "sdl"))]
+fn run_windowed(_processor: &mut Processor, _options: &Options) -> Result<(), Chip8Error> {
+    exit_with("This build has no SDL support, run with --headless instead".to_string())
+}
+
+#[cfg(feature = "terminal")]
+fn run_terminal(processor: &mut Processor, options: &Options) -> Result<(), Chip8Error> {
+    let settings = terminal::TerminalSettings {
+        glyphs: options.glyphs,
+        filter: options.filter,
+    };
+    let mut screen =
+        terminal::TerminalScreen::new(settings, options.palettes.clone(), &options.keymap)
+            .unwrap_or_else(|e| exit_with(e));
+    // The terminal has to be back to normal before anything else is printed
+    let result = run_limited(processor, &mut screen, options.frames);
+    drop(screen);
+    result
+}
+
+#[cfg(not(feature = "terminal"))]
+fn run_terminal(_processor: &mut Processor, _options: &Options) -> Result<(), Chip8Error> {
+    exit_with("This build has no terminal support, run with --headless instead".to_string())
+}
+
+#[cfg_attr(not(any(feature = "sdl", feature = "terminal")), allow(dead_code))]
+fn run_limited<F: Frontend>(
+    processor: &mut Processor,
+    frontend: &mut F,
+    frames: Option<u64>,
+) -> Result<(), Chip8Error> {
+    match frames {
+        Some(frames) => processor.run(&mut FrameLimit::new(frontend, frames)),
+        None => processor.run(frontend),
+    }
 }
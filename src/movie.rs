@@ -0,0 +1,332 @@
+/*
+    Recorded input for replaying a session exactly.
+
+    A movie holds the keys that were down on every frame, along with everything
+    else that decides how the program runs: a hash of the ROM, the platform,
+    the quirks, the speed and the RNG seed. Since the machine is deterministic,
+    feeding the same keys to a fresh machine set up the same way reproduces the
+    session. Every `CHECKSUM_INTERVAL` frames the movie also records a checksum
+    of the whole machine state, so playback notices the moment it stops
+    matching the recording rather than just ending up somewhere else.
+
+    The file starts with a magic number and a version like a save state, and
+    all numbers are little endian.
+*/
+
+use crate::error::Chip8Error;
+use crate::processor::{Processor, KEYBOARD_SIZE};
+use crate::quirks::{Platform, Quirks};
+use crate::snapshot::{self, Reader, Snapshot};
+use std::fs;
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"C8MV";
+const VERSION: u16 = 1;
+
+/// Frames between state checksums.
+pub const CHECKSUM_INTERVAL: u64 = 60;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Movie {
+    /// `rom_hash` of the ROM the movie was recorded with.
+    pub rom_hash: u64,
+    pub platform: Platform,
+    pub quirks: Quirks,
+    pub cycles_per_frame: u32,
+    pub seed: u64,
+    /// The keys held on each frame.
+    pub frames: Vec<[bool; KEYBOARD_SIZE]>,
+    /// State checksums, each with the number of frames run before it was taken.
+    pub checksums: Vec<(u64, u64)>,
+}
+
+impl Movie {
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// The checksum recorded after `frames` frames, if there is one.
+    pub fn checksum_at(&self, frames: u64) -> Option<u64> {
+        self.checksums
+            .binary_search_by_key(&frames, |&(at, _)| at)
+            .ok()
+            .map(|i| self.checksums[i].1)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(32 + self.frames.len() * 2 + self.checksums.len() * 12);
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        out.extend_from_slice(&self.rom_hash.to_le_bytes());
+        out.push(snapshot::platform_id(self.platform));
+        out.push(snapshot::quirk_bits(self.quirks));
+        out.extend_from_slice(&self.cycles_per_frame.to_le_bytes());
+        out.extend_from_slice(&self.seed.to_le_bytes());
+
+        out.extend_from_slice(&(self.frames.len() as u32).to_le_bytes());
+        for keys in &self.frames {
+            let bits = keys
+                .iter()
+                .enumerate()
+                .fold(0u16, |bits, (key, &down)| bits | (down as u16) << key);
+            out.extend_from_slice(&bits.to_le_bytes());
+        }
+        out.extend_from_slice(&(self.checksums.len() as u32).to_le_bytes());
+        for &(frames, checksum) in &self.checksums {
+            out.extend_from_slice(&(frames as u32).to_le_bytes());
+            out.extend_from_slice(&checksum.to_le_bytes());
+        }
+        out
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Movie, Chip8Error> {
+        let mut reader = Reader::new(data, invalid);
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(invalid("not a movie"));
+        }
+        let version = reader.u16()?;
+        if version != VERSION {
+            return Err(Chip8Error::InvalidMovie(format!(
+                "unsupported version {}",
+                version
+            )));
+        }
+
+        let rom_hash = reader.u64()?;
+        let platform = *Platform::ALL
+            .get(reader.u8()? as usize)
+            .ok_or_else(|| invalid("unknown platform"))?;
+        let quirks = snapshot::quirks_from_bits(reader.u8()?);
+        let cycles_per_frame = reader.u32()?;
+        let seed = reader.u64()?;
+
+        let frame_count = reader.u32()? as usize;
+        let mut frames = Vec::with_capacity(frame_count.min(data.len() / 2));
+        for _ in 0..frame_count {
+            let bits = reader.u16()?;
+            let mut keys = [false; KEYBOARD_SIZE];
+            for (key, down) in keys.iter_mut().enumerate() {
+                *down = bits & 1 << key != 0;
+            }
+            frames.push(keys);
+        }
+        let checksum_count = reader.u32()? as usize;
+        let mut checksums = Vec::with_capacity(checksum_count.min(data.len() / 12));
+        for _ in 0..checksum_count {
+            checksums.push((reader.u32()? as u64, reader.u64()?));
+        }
+        if !checksums.windows(2).all(|pair| pair[0].0 < pair[1].0) {
+            return Err(invalid("checksums are out of order"));
+        }
+
+        if !reader.is_empty() {
+            return Err(invalid("unexpected data at the end"));
+        }
+
+        Ok(Movie {
+            rom_hash,
+            platform,
+            quirks,
+            cycles_per_frame,
+            seed,
+            frames,
+            checksums,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Chip8Error> {
+        fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Movie, Chip8Error> {
+        Movie::from_bytes(&fs::read(path)?)
+    }
+}
+
+/// Identifies a ROM, so a movie is only played back on the one it was
+/// recorded with. FNV-1a, which is plenty to tell ROMs apart.
+pub fn rom_hash(rom: &[u8]) -> u64 {
+    fnv1a(rom)
+}
+
+/// A checksum of the complete machine state.
+pub fn checksum(snapshot: &Snapshot) -> u64 {
+    fnv1a(&snapshot.to_bytes())
+}
+
+fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xCBF2_9CE4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01B3)
+    })
+}
+
+fn invalid(reason: &str) -> Chip8Error {
+    Chip8Error::InvalidMovie(reason.to_string())
+}
+
+// A movie being recorded or played back by a `Processor`
+pub(crate) struct MovieState {
+    movie: Movie,
+    recording: bool,
+    // Frames played back so far
+    frame: usize,
+    // Frames run when playback first stopped matching the recording
+    desync: Option<u64>,
+    // News for the user, such as a desync or the end of playback
+    message: Option<String>,
+}
+
+impl Processor {
+    /// Starts recording a movie. Call it on a freshly loaded ROM, before the
+    /// first frame, so playback can start from the same place.
+    ///
+    /// Only what reaches the program through the keypad is recorded, so
+    /// loading states and changing the speed are refused while recording.
+    /// Frames while the debugger has the machine paused are left out.
+    pub fn record_movie(&mut self) {
+        let movie = Movie {
+            rom_hash: self.rom_hash(),
+            platform: self.platform(),
+            quirks: self.quirks(),
+            cycles_per_frame: self.cycles_per_frame(),
+            seed: self.seed(),
+            frames: Vec::new(),
+            checksums: Vec::new(),
+        };
+        *self.movie_state_mut() = Some(MovieState {
+            movie,
+            recording: true,
+            frame: 0,
+            desync: None,
+            message: None,
+        });
+    }
+
+    /// Starts playing back `movie` on a freshly loaded ROM, taking its quirks,
+    /// speed and seed. Its keys replace the frontend's until it runs out.
+    pub fn play_movie(&mut self, movie: Movie) -> Result<(), Chip8Error> {
+        if movie.rom_hash != self.rom_hash() {
+            return Err(Chip8Error::InvalidMovie(
+                "it was recorded with a different ROM".to_string(),
+            ));
+        }
+        if movie.platform != self.platform() {
+            return Err(Chip8Error::InvalidMovie(format!(
+                "it was recorded on the {} platform",
+                movie.platform
+            )));
+        }
+        self.set_quirks(movie.quirks);
+        self.set_cycles_per_frame(movie.cycles_per_frame);
+        self.seed_rng(movie.seed);
+        *self.movie_state_mut() = Some(MovieState {
+            movie,
+            recording: false,
+            frame: 0,
+            desync: None,
+            message: None,
+        });
+        Ok(())
+    }
+
+    /// Stops recording or playing, returning the movie. A recording gets a
+    /// final checksum so playback can confirm where it ended up.
+    pub fn stop_movie(&mut self) -> Option<Movie> {
+        let state = self.movie_state_mut().take()?;
+        let mut movie = state.movie;
+        let frames = movie.len() as u64;
+        if state.recording && frames > 0 && movie.checksum_at(frames).is_none() {
+            movie.checksums.push((frames, checksum(&self.snapshot())));
+        }
+        Some(movie)
+    }
+
+    pub fn is_recording_movie(&self) -> bool {
+        self.movie_state().is_some_and(|state| state.recording)
+    }
+
+    /// True while a movie has frames left to play.
+    pub fn is_playing_movie(&self) -> bool {
+        self.movie_state()
+            .is_some_and(|state| !state.recording && state.frame < state.movie.len())
+    }
+
+    /// How many frames had run when playback first stopped matching the
+    /// recording, if it has.
+    pub fn movie_desync(&self) -> Option<u64> {
+        self.movie_state().and_then(|state| state.desync)
+    }
+
+    /// Takes news about the movie for the user, such as a desync.
+    pub fn take_movie_message(&mut self) -> Option<String> {
+        self.movie_state_mut().as_mut()?.message.take()
+    }
+
+    // Called before each frame that runs: records the keys, or replaces them
+    // with the recorded ones
+    pub(crate) fn movie_before_frame(&mut self) {
+        let keys = *self.keys();
+        let Some(state) = self.movie_state_mut().as_mut() else {
+            return;
+        };
+        if state.recording {
+            state.movie.frames.push(keys);
+        } else if let Some(&recorded) = state.movie.frames.get(state.frame) {
+            self.set_keys(recorded);
+        }
+    }
+
+    // Called after each frame that runs: takes or compares a checksum when one
+    // is due
+    pub(crate) fn movie_after_frame(&mut self) {
+        let Some(state) = self.movie_state_mut().as_mut() else {
+            return;
+        };
+        if state.recording {
+            let frames = state.movie.len() as u64;
+            if frames.is_multiple_of(CHECKSUM_INTERVAL) {
+                let checksum = checksum(&self.snapshot());
+                if let Some(state) = self.movie_state_mut() {
+                    state.movie.checksums.push((frames, checksum));
+                }
+            }
+            return;
+        }
+
+        if state.frame >= state.movie.len() {
+            return;
+        }
+        state.frame += 1;
+        let frames = state.frame as u64;
+        let expected = state.movie.checksum_at(frames);
+        let actual = match expected {
+            Some(_) if state.desync.is_none() => Some(checksum(&self.snapshot())),
+            _ => None,
+        };
+        let Some(state) = self.movie_state_mut() else {
+            return;
+        };
+        let mut news = Vec::new();
+        if actual.is_some() && actual != expected {
+            state.desync = Some(frames);
+            news.push(format!(
+                "Movie desynced: the state after frame {} differs from the recording",
+                frames
+            ));
+        }
+        if state.frame == state.movie.len() {
+            news.push(format!("Movie finished after {} frames", frames));
+        }
+        if !news.is_empty() {
+            state.message = Some(news.join("\n"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
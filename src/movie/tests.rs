@@ -0,0 +1,174 @@
+// The program adds a random number to V1 and counts the frames key 0 is held
+// in V3, so both the seed and the keys show up in the machine state
+
+use super::*;
+use crate::debugger::DebugCommand;
+use crate::{Command, Processor};
+
+const PROGRAM: [u16; 5] = [
+    0xC0FF, // RND V0, 0xFF
+    0x8104, // ADD V1, V0
+    0xE29E, // SKP V2
+    0x7301, // ADD V3, 0x01
+    0x1200, // JP 0x200
+];
+// Long enough for two checksums along the way and one at the end
+const FRAMES: u64 = 2 * CHECKSUM_INTERVAL + 10;
+
+fn processor(seed: u64) -> Processor {
+    let platform = Platform::XoChip;
+    let mut processor = Processor::with_program(platform, platform.quirks(), &PROGRAM);
+    processor.seed_rng(seed);
+    processor
+}
+
+// Key 0 is held for a few frames early on
+fn keys_for(frame: u64) -> [bool; KEYBOARD_SIZE] {
+    let mut keys = [false; KEYBOARD_SIZE];
+    keys[0] = (20..30).contains(&frame);
+    keys
+}
+
+fn record(seed: u64) -> Movie {
+    let mut processor = processor(seed);
+    processor.record_movie();
+    for frame in 0..FRAMES {
+        processor.set_keys(keys_for(frame));
+        processor.run_frame().unwrap();
+    }
+    processor.stop_movie().unwrap()
+}
+
+// Plays `movie` to the end, returning the machine and the messages it left
+// for the user along the way
+fn play(movie: Movie, seed: u64) -> (Processor, Vec<String>) {
+    let mut processor = processor(seed);
+    processor.play_movie(movie).unwrap();
+    let mut messages = Vec::new();
+    while processor.is_playing_movie() {
+        processor.run_frame().unwrap();
+        messages.extend(processor.take_movie_message());
+    }
+    (processor, messages)
+}
+
+#[test]
+fn records_keys_and_checksums() {
+    let movie = record(1);
+    assert_eq!(movie.len() as u64, FRAMES);
+    assert_eq!(movie.seed, 1);
+    assert_eq!(movie.frames[25], keys_for(25));
+    let at: Vec<u64> = movie.checksums.iter().map(|&(at, _)| at).collect();
+    assert_eq!(at, [CHECKSUM_INTERVAL, 2 * CHECKSUM_INTERVAL, FRAMES]);
+    assert!(movie.checksum_at(CHECKSUM_INTERVAL).is_some());
+    assert!(movie.checksum_at(CHECKSUM_INTERVAL + 1).is_none());
+}
+
+#[test]
+fn round_trips_through_bytes() {
+    let movie = record(1);
+    assert_eq!(Movie::from_bytes(&movie.to_bytes()).unwrap(), movie);
+}
+
+#[test]
+fn rejects_damaged_files() {
+    let movie = record(1);
+    let bytes = movie.to_bytes();
+    for damaged in [
+        &bytes[..bytes.len() - 1],
+        &[bytes.as_slice(), &[0]].concat(),
+        b"C8SS",
+    ] {
+        assert!(matches!(
+            Movie::from_bytes(damaged),
+            Err(Chip8Error::InvalidMovie(_))
+        ));
+    }
+
+    let mut shuffled = movie;
+    shuffled.checksums.swap(0, 1);
+    assert!(Movie::from_bytes(&shuffled.to_bytes()).is_err());
+}
+
+#[test]
+fn plays_back_without_desyncing() {
+    let mut recorder = processor(1);
+    recorder.record_movie();
+    for frame in 0..FRAMES {
+        recorder.set_keys(keys_for(frame));
+        recorder.run_frame().unwrap();
+    }
+    let movie = recorder.stop_movie().unwrap();
+
+    // The seed given here is replaced by the movie's
+    let (player, messages) = play(movie, 2);
+    assert_eq!(player.movie_desync(), None);
+    assert_eq!(
+        messages,
+        [format!("Movie finished after {} frames", FRAMES)]
+    );
+    assert_eq!(player.snapshot(), recorder.snapshot());
+}
+
+#[test]
+fn the_debugger_cannot_step_a_recording() {
+    let mut recorder = processor(1);
+    recorder.record_movie();
+    for frame in 0..FRAMES {
+        if frame == 10 {
+            let refused = "Not available while a movie is recording or playing";
+            for command in [DebugCommand::Pause, DebugCommand::Step] {
+                let message = recorder.handle_command(Command::Debug(command));
+                assert_eq!(message, refused);
+            }
+            // Looking is still fine
+            let message = recorder.handle_command(Command::Debug(DebugCommand::Registers));
+            assert_ne!(message, refused);
+        }
+        recorder.set_keys(keys_for(frame));
+        recorder.run_frame().unwrap();
+    }
+    assert!(!recorder.debugger().is_paused());
+    let movie = recorder.stop_movie().unwrap();
+
+    let (player, _) = play(movie, 1);
+    assert_eq!(player.movie_desync(), None);
+    assert_eq!(player.snapshot(), recorder.snapshot());
+}
+
+#[test]
+fn a_different_seed_desyncs_at_the_first_checksum() {
+    let mut movie = record(1);
+    movie.seed = 2;
+    assert_eq!(play(movie, 1).0.movie_desync(), Some(CHECKSUM_INTERVAL));
+}
+
+#[test]
+fn a_different_key_desyncs_at_the_next_checksum() {
+    let mut movie = record(1);
+    movie.frames[(CHECKSUM_INTERVAL + 5) as usize][0] = true;
+    let (player, messages) = play(movie, 1);
+    assert_eq!(player.movie_desync(), Some(2 * CHECKSUM_INTERVAL));
+    // Reported once, even though the final checksum differs too
+    assert_eq!(
+        messages,
+        [
+            format!(
+                "Movie desynced: the state after frame {} differs from the recording",
+                2 * CHECKSUM_INTERVAL
+            ),
+            format!("Movie finished after {} frames", FRAMES),
+        ]
+    );
+}
+
+#[test]
+fn refuses_other_roms_and_platforms() {
+    let platform = Platform::XoChip;
+    let mut other = Processor::with_program(platform, platform.quirks(), &[0x1200]);
+    assert!(other.play_movie(record(1)).is_err());
+
+    let mut movie = record(1);
+    movie.platform = Platform::SuperChip;
+    assert!(processor(1).play_movie(movie).is_err());
+}
@@ -0,0 +1,221 @@
+/*
+    Colours used to draw the framebuffer.
+
+    Each pixel value gets its own colour: off, lit on the first plane, lit on the
+    second plane and lit on both. Plain CHIP-8 programs only use the first two.
+    A handful of presets come built in, and `Palettes` keeps them along with any
+    the user defines so the frontend can cycle through them.
+*/
+
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Palette {
+    pub colours: [Rgb; 4],
+}
+
+/// The built in palettes, named as `--palette` takes them.
+pub const PRESETS: [(&str, Palette); 5] = [
+    (
+        "classic",
+        Palette {
+            colours: [
+                Rgb(0, 0, 0),
+                Rgb(255, 255, 255),
+                Rgb(170, 170, 170),
+                Rgb(85, 85, 85),
+            ],
+        },
+    ),
+    // Green phosphor, like an old monochrome monitor
+    (
+        "green",
+        Palette {
+            colours: [
+                Rgb(0x0B, 0x1F, 0x0B),
+                Rgb(0x33, 0xFF, 0x66),
+                Rgb(0x22, 0xAA, 0x44),
+                Rgb(0x16, 0x7A, 0x30),
+            ],
+        },
+    ),
+    (
+        "amber",
+        Palette {
+            colours: [
+                Rgb(0x1A, 0x0F, 0x00),
+                Rgb(0xFF, 0xB0, 0x00),
+                Rgb(0xB3, 0x7B, 0x00),
+                Rgb(0x66, 0x46, 0x00),
+            ],
+        },
+    ),
+    // The original Game Boy's shades, dark pixels on a light screen. Its two
+    // lightest are too close to tell apart, so the second plane shares the
+    // mid-dark one with overlaps
+    (
+        "gameboy",
+        Palette {
+            colours: [
+                Rgb(0x9B, 0xBC, 0x0F),
+                Rgb(0x0F, 0x38, 0x0F),
+                Rgb(0x30, 0x62, 0x30),
+                Rgb(0x30, 0x62, 0x30),
+            ],
+        },
+    ),
+    // Fully saturated so the planes are easy to tell apart
+    (
+        "contrast",
+        Palette {
+            colours: [
+                Rgb(0, 0, 0),
+                Rgb(255, 255, 255),
+                Rgb(255, 255, 0),
+                Rgb(0, 255, 255),
+            ],
+        },
+    ),
+];
+
+impl Default for Palette {
+    fn default() -> Self {
+        PRESETS[0].1
+    }
+}
+
+impl Palette {
+    /// The colour for a pixel value from the framebuffer.
+    pub fn colour(&self, pixel: u8) -> Rgb {
+        self.colours[pixel as usize & 0x3]
+    }
+
+    /// The built in palette called `name`.
+    pub fn preset(name: &str) -> Option<Palette> {
+        PRESETS
+            .iter()
+            .find(|(preset, _)| preset.eq_ignore_ascii_case(name))
+            .map(|&(_, palette)| palette)
+    }
+
+    /// Builds a palette from two to four colours, starting with the
+    /// background. Colours that are left out are shades between the ones given.
+    pub fn from_colours(colours: &[Rgb]) -> Result<Palette, String> {
+        let shade = |from: Rgb, to: Rgb, thirds: u16| {
+            let mix = |a: u8, b: u8| ((a as u16 * (3 - thirds) + b as u16 * thirds) / 3) as u8;
+            Rgb(mix(from.0, to.0), mix(from.1, to.1), mix(from.2, to.2))
+        };
+        let colours = match *colours {
+            [off, on] => [off, on, shade(off, on, 2), shade(off, on, 1)],
+            [off, first, second] => [off, first, second, shade(first, second, 1)],
+            [off, first, second, both] => [off, first, second, both],
+            _ => {
+                return Err(format!(
+                    "Expected between 2 and 4 colours in a palette, got {}",
+                    colours.len()
+                ))
+            }
+        };
+        Ok(Palette { colours })
+    }
+}
+
+impl FromStr for Rgb {
+    type Err = String;
+
+    /// Parses a hex colour such as `ffcc00` or `#ffcc00`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.trim().trim_start_matches('#');
+        let value = u32::from_str_radix(hex, 16)
+            .ok()
+            .filter(|_| hex.len() == 6)
+            .ok_or_else(|| format!("Expected a colour like ffcc00, got '{}'", s))?;
+        Ok(Rgb((value >> 16) as u8, (value >> 8) as u8, value as u8))
+    }
+}
+
+impl FromStr for Palette {
+    type Err = String;
+
+    /// Parses the name of a preset, or a comma separated list of two to four
+    /// hex colours starting with the background.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(palette) = Palette::preset(s.trim()) {
+            return Ok(palette);
+        }
+        let colours = s
+            .split(',')
+            .map(str::parse)
+            .collect::<Result<Vec<Rgb>, _>>()
+            .map_err(|e| {
+                let names: Vec<_> = PRESETS.iter().map(|(name, _)| *name).collect();
+                format!("{}, or one of the palettes {}", e, names.join(", "))
+            })?;
+        Palette::from_colours(&colours)
+    }
+}
+
+/// The palettes to cycle through, presets first and then the user's own, and
+/// which one is in use.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Palettes {
+    palettes: Vec<(String, Palette)>,
+    current: usize,
+}
+
+impl Default for Palettes {
+    fn default() -> Self {
+        Palettes {
+            palettes: PRESETS
+                .iter()
+                .map(|&(name, palette)| (name.to_string(), palette))
+                .collect(),
+            current: 0,
+        }
+    }
+}
+
+impl Palettes {
+    /// Adds a palette to the cycle, replacing any with the same name.
+    pub fn add(&mut self, name: &str, palette: Palette) {
+        match self.position(name) {
+            Some(i) => self.palettes[i].1 = palette,
+            None => self.palettes.push((name.to_string(), palette)),
+        }
+    }
+
+    /// Switches to the palette called `name`, or to one written out as hex
+    /// colours, which joins the cycle as "custom".
+    pub fn select(&mut self, name: &str) -> Result<(), String> {
+        if let Some(i) = self.position(name.trim()) {
+            self.current = i;
+            return Ok(());
+        }
+        let palette = name.parse()?;
+        self.add("custom", palette);
+        self.current = self.position("custom").unwrap();
+        Ok(())
+    }
+
+    /// Moves on to the next palette, returning to the first after the last.
+    pub fn next(&mut self) {
+        self.current = (self.current + 1) % self.palettes.len();
+    }
+
+    pub fn current(&self) -> Palette {
+        self.palettes[self.current].1
+    }
+
+    pub fn name(&self) -> &str {
+        &self.palettes[self.current].0
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.palettes
+            .iter()
+            .position(|(existing, _)| existing.eq_ignore_ascii_case(name))
+    }
+}
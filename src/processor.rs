@@ -2,21 +2,35 @@
     Chip-8 technical reference - http://devernay.free.fr/hacks/chip8/C8TECH10.HTM
 */
 
+use crate::audio::Sound;
+use crate::debugger::{self, Access, Accessor, Debugger};
+use crate::error::Chip8Error;
+use crate::framebuffer::{
+    Framebuffer, HIRES_HEIGHT, HIRES_WIDTH, LORES_HEIGHT, LORES_WIDTH, PLANE_COUNT,
+};
+use crate::frontend::{Command, Frontend, Status};
+use crate::instruction::Instruction;
+use crate::movie::{self, MovieState};
+use crate::quirks::{Platform, Quirks};
+use crate::rewind::{Rewind, DEFAULT_REWIND_MEMORY};
+use crate::rng::Rng;
+use crate::scheduler::Scheduler;
+use crate::snapshot::{self, Snapshot};
 use std::fs;
-extern crate rand;
-use crate::display::GameCanvas;
-use rand::Rng;
-use std::{thread, time};
-
-const RAM: usize = 4096;
-pub const DISPLAY_WIDTH: usize = 64;
-pub const DISPLAY_HEIGHT: usize = 32;
+use std::ops::Range;
+use std::path::PathBuf;
+
 const REGISTER_COUNT: usize = 16;
 const STACK_SIZE: usize = 16;
 const INSTRUCTION_SIZE: usize = 2;
-const CLOCK_SPEED: u64 = 2; // Clock speed of CHIP-8 is usually 500Hz
 pub const KEYBOARD_SIZE: usize = 16;
-const FRAME_RATE: u32 = 8;
+// Clock speed of CHIP-8 is usually 500Hz, which is about 8 instructions per 60Hz frame
+pub const DEFAULT_CYCLES_PER_FRAME: u32 = 8;
+const MAX_CYCLES_PER_FRAME: u32 = 100_000;
+const BIG_FONT_ADDRESS: usize = FONT.len(); // SUPER-CHIP font sits right after the small one
+const RPL_FLAG_COUNT: usize = 16;
+pub const AUDIO_PATTERN_SIZE: usize = 16;
+const DEFAULT_PITCH: u8 = 64; // Plays the audio pattern at 4000 bits per second
 
 const FONT: [u8; 5 * 16] = [
     0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
@@ -37,6 +51,26 @@ const FONT: [u8; 5 * 16] = [
     0xF0, 0x80, 0xF0, 0x80, 0x80, // F
 ];
 
+// 8x10 SUPER-CHIP digits. The original only had 0-9, A-F are the ones Octo uses.
+const BIG_FONT: [u8; 10 * 16] = [
+    0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C, // 0
+    0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, // 1
+    0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF, // 2
+    0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C, // 3
+    0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C, // 5
+    0x3E, 0x7C, 0xE0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C, // 6
+    0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60, // 7
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C, // 8
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C, // 9
+    0x18, 0x3C, 0x66, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFE, 0xC3, 0xC3, 0xFE, 0xFE, 0xC3, 0xC3, 0xFE, 0xFC, // B
+    0x3C, 0x7E, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0x7E, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+
 #[cfg(feature = "debug_mode")]
 macro_rules! debug {
     ($x:expr) => {
@@ -52,8 +86,8 @@ macro_rules! debug {
 }
 
 pub struct Processor {
-    ram: [u8; RAM],
-    display: [[u8; DISPLAY_WIDTH]; DISPLAY_HEIGHT],
+    ram: Vec<u8>,
+    display: Framebuffer,
     v: [u8; REGISTER_COUNT],
     stack: [usize; STACK_SIZE],
     i_register: usize,
@@ -63,20 +97,46 @@ pub struct Processor {
     sp: usize,
     keyboard_presses: [bool; KEYBOARD_SIZE],
     is_waiting_for_input: bool,
-    title: String,
+    // The key Fx0A saw go down, which it takes once it comes back up
+    pressed_key: Option<u8>,
+    platform: Platform,
+    quirks: Quirks,
+    rpl: [u8; RPL_FLAG_COUNT],
+    exited: bool,
+    selected_planes: u8,
+    audio_pattern: Option<[u8; AUDIO_PATTERN_SIZE]>,
+    pitch: u8,
+    cycles_per_frame: u32,
+    rng: Rng,
+    rom_path: Option<PathBuf>,
+    history: Rewind,
+    debugger: Debugger,
+    rom_hash: u64,
+    movie: Option<MovieState>,
+}
+
+impl Default for Processor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Processor {
-    pub fn new(rom: &str) -> Processor {
+    pub fn new() -> Processor {
+        Processor::with_platform(Platform::CosmacVip, Quirks::default())
+    }
+
+    /// Creates a machine for the given platform. The platform decides which
+    /// instructions exist, the quirks decide how the shared ones behave.
+    pub fn with_platform(platform: Platform, quirks: Quirks) -> Processor {
         // Load in the default sprites
-        let mut ram = [0; RAM];
-        for i in 0..FONT.len() {
-            ram[i] = FONT[i];
-        }
+        let mut ram = vec![0; platform.memory_size()];
+        ram[..FONT.len()].copy_from_slice(&FONT);
+        ram[BIG_FONT_ADDRESS..BIG_FONT_ADDRESS + BIG_FONT.len()].copy_from_slice(&BIG_FONT);
 
         Processor {
             ram,
-            display: [[0; DISPLAY_WIDTH]; DISPLAY_HEIGHT],
+            display: Framebuffer::default(),
             v: [0; REGISTER_COUNT],
             stack: [0; STACK_SIZE],
             i_register: 0,
@@ -86,189 +146,570 @@ impl Processor {
             sp: 0,
             keyboard_presses: [false; KEYBOARD_SIZE],
             is_waiting_for_input: false,
-            title: rom.to_string(),
+            pressed_key: None,
+            platform,
+            quirks,
+            rpl: [0; RPL_FLAG_COUNT],
+            exited: false,
+            selected_planes: 1,
+            audio_pattern: None,
+            pitch: DEFAULT_PITCH,
+            cycles_per_frame: DEFAULT_CYCLES_PER_FRAME,
+            rng: Rng::from_entropy(),
+            rom_path: None,
+            history: Rewind::new(DEFAULT_REWIND_MEMORY),
+            debugger: Debugger::default(),
+            rom_hash: movie::rom_hash(&[]),
+            movie: None,
         }
     }
 
-    pub fn load_rom(&mut self, path: &str) {
-        if let Ok(data) = fs::read(path) {
-            for (i, item) in data.iter().enumerate() {
-                self.ram[i + 512] = *item;
-            }
-        } else {
-            panic!("Unable to load rom");
+    pub fn load_rom(&mut self, path: &str) -> Result<(), Chip8Error> {
+        let data = fs::read(path)?;
+        self.load_program(&data)?;
+        self.rom_path = Some(PathBuf::from(path));
+        Ok(())
+    }
+
+    /// Loads a program that is already in memory, such as one just built with
+    /// the assembler, at 0x200.
+    pub fn load_program(&mut self, program: &[u8]) -> Result<(), Chip8Error> {
+        let capacity = self.ram.len() - 512;
+        if program.len() > capacity {
+            return Err(Chip8Error::RomTooLarge {
+                size: program.len(),
+                capacity,
+            });
         }
+        self.ram[512..512 + program.len()].copy_from_slice(program);
+        self.rom_hash = movie::rom_hash(program);
+        Ok(())
     }
 
-    pub fn start(&mut self) {
-        let mut game_canvas = GameCanvas::new(&self.title);
-        let mut freq = 0;
-        loop {
-            if let Some(input) = game_canvas.read_keyboard_inputs() {
-                self.keyboard_presses = input;
-            } else {
+    /// Writes `bytes` into memory at `address` before the program runs, for
+    /// test ROMs that read their settings from memory.
+    pub fn poke(&mut self, address: usize, bytes: &[u8]) -> Result<(), Chip8Error> {
+        let range = self.memory_range(address, bytes.len())?;
+        self.ram[range].copy_from_slice(bytes);
+        Ok(())
+    }
+
+    /// Runs the machine in real time until the frontend asks to quit or the
+    /// program exits. Timers tick at 60Hz and a frame is presented once per tick.
+    ///
+    /// If the program faults, the frontend is shown the error and it is returned.
+    pub fn run<F: Frontend>(&mut self, frontend: &mut F) -> Result<(), Chip8Error> {
+        let mut scheduler = Scheduler::new();
+        while !self.exited {
+            let Some(input) = frontend.read_keyboard_inputs() else {
                 break;
+            };
+            self.keyboard_presses = input;
+            let mut rewinding = false;
+            for command in frontend.take_commands() {
+                rewinding |= matches!(command, Command::Rewind(_)) && self.movie.is_none();
+                let message = self.handle_command(command);
+                if !message.is_empty() {
+                    frontend.show_message(&message);
+                }
             }
 
-            self.tick();
-            if freq == 0 {
-                if !self.is_waiting_for_input {
-                    if self.delay_register > 0 {
-                        self.delay_register -= 1;
+            // Normally one frame, more if we have fallen behind and need to
+            // catch the timers up. Only the latest frame is drawn. While
+            // rewinding the program is paused, but the clock keeps pace so it
+            // does not rush to catch up afterwards.
+            let frames = scheduler.wait_for_frame();
+            if !rewinding {
+                for _ in 0..frames {
+                    let paused = self.debugger.is_paused();
+                    if let Err(e) = self.run_frame() {
+                        frontend.report_error(&e);
+                        return Err(e);
                     }
-                    if self.sound_register > 0 {
-                        self.sound_register -= 1;
+                    if !paused {
+                        self.history.push(&self.snapshot());
                     }
                 }
-                game_canvas.draw_frame(&self.display);
-                freq = FRAME_RATE;
             }
-            thread::sleep(time::Duration::from_millis(CLOCK_SPEED));
-            freq -= 1;
+            if let Some(report) = self.debugger.take_report() {
+                frontend.show_message(&report);
+            }
+            if let Some(message) = self.take_movie_message() {
+                frontend.show_message(&message);
+            }
+            frontend.play_sound(self.sound());
+            frontend.show_status(&self.status());
+            frontend.draw_frame(&self.display);
         }
+        Ok(())
     }
 
-    pub fn tick(&mut self) {
-        // Get the current opcode
-        let opcode = self.get_current_opcode();
+    // Carries out a command and describes the outcome for the user
+    pub(crate) fn handle_command(&mut self, command: Command) -> String {
+        // A movie only has the keypad to go on, so nothing else may change
+        // how the program runs, including the debugger stepping it or
+        // stopping it partway through a frame
+        let changes_run = match &command {
+            Command::SpeedUp | Command::SlowDown | Command::LoadState(_) => true,
+            Command::Debug(command) => command.controls_run(),
+            Command::SaveState(_) | Command::Rewind(_) => false,
+        };
+        if changes_run && self.movie.is_some() {
+            return "Not available while a movie is recording or playing".to_string();
+        }
+        match command {
+            Command::SpeedUp => {
+                self.set_cycles_per_frame(self.cycles_per_frame * 2);
+                format!("Speed: {} instructions per frame", self.cycles_per_frame)
+            }
+            Command::SlowDown => {
+                self.set_cycles_per_frame(self.cycles_per_frame / 2);
+                format!("Speed: {} instructions per frame", self.cycles_per_frame)
+            }
+            Command::SaveState(slot) => {
+                let Some(path) = self.slot_path(slot) else {
+                    return "Save states need a ROM loaded from a file".to_string();
+                };
+                match self.snapshot().save(&path) {
+                    Ok(()) => format!("Saved state to slot {}", slot),
+                    Err(e) => format!("Unable to save slot {}: {}", slot, e),
+                }
+            }
+            Command::Debug(command) => self.debug(command),
+            // Sent every frame the key is held, so it goes quietly, and movies
+            // can't follow time going backwards
+            Command::Rewind(frames) => {
+                if self.movie.is_none() {
+                    self.rewind(frames as usize);
+                }
+                String::new()
+            }
+            Command::LoadState(slot) => {
+                let Some(path) = self.slot_path(slot) else {
+                    return "Save states need a ROM loaded from a file".to_string();
+                };
+                match Snapshot::load(&path) {
+                    Ok(state) => {
+                        self.restore(state);
+                        format!("Loaded state from slot {}", slot)
+                    }
+                    Err(e) => format!("Unable to load slot {}: {}", slot, e),
+                }
+            }
+        }
+    }
 
-        // Run the opcode
-        self.run_opcode(opcode);
+    fn slot_path(&self, slot: u8) -> Option<PathBuf> {
+        let rom = self.rom_path.as_ref()?;
+        Some(snapshot::slot_path(rom, slot))
     }
 
-    fn get_current_opcode(&self) -> u16 {
-        (self.ram[self.pc] as u16) << 8 | (self.ram[self.pc + 1] as u16)
+    /// Steps back up to `frames` frames through the history recorded by `run`.
+    /// Returns false if there was nothing earlier to go back to.
+    pub fn rewind(&mut self, frames: usize) -> bool {
+        match self.history.rewind(frames) {
+            Some(state) => {
+                self.restore(state);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Caps the memory used to record frames for rewinding, in bytes. 0 turns
+    /// rewinding off.
+    pub fn set_rewind_memory(&mut self, bytes: usize) {
+        self.history.set_limit(bytes);
+    }
+
+    /// Copies out everything needed to put the machine back exactly as it is
+    /// now. The speed and the loaded ROM's path are settings, not state, and
+    /// are left out.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            platform: self.platform,
+            quirks: self.quirks,
+            ram: self.ram.clone(),
+            display: self.display.clone(),
+            v: self.v,
+            stack: self.stack,
+            sp: self.sp,
+            pc: self.pc,
+            i_register: self.i_register,
+            delay_register: self.delay_register,
+            sound_register: self.sound_register,
+            keyboard_presses: self.keyboard_presses,
+            is_waiting_for_input: self.is_waiting_for_input,
+            pressed_key: self.pressed_key,
+            rpl: self.rpl,
+            exited: self.exited,
+            selected_planes: self.selected_planes,
+            audio_pattern: self.audio_pattern,
+            pitch: self.pitch,
+            rng: self.rng,
+        }
     }
 
-    fn run_opcode(&mut self, opcode: u16) {
-        // Split the opcode into nibbles (4bits)
-        let nibbles = (
-            (opcode & 0xF000) >> 12 as u8,
-            (opcode & 0x0F00) >> 8 as u8,
-            (opcode & 0x00F0) >> 4 as u8,
-            (opcode & 0x000F) as u8,
-        );
+    /// Puts the machine back into the state captured by `snapshot`.
+    pub fn restore(&mut self, snapshot: Snapshot) {
+        self.platform = snapshot.platform;
+        self.quirks = snapshot.quirks;
+        self.ram = snapshot.ram;
+        self.display = snapshot.display;
+        self.v = snapshot.v;
+        self.stack = snapshot.stack;
+        self.sp = snapshot.sp;
+        self.pc = snapshot.pc;
+        self.i_register = snapshot.i_register;
+        self.delay_register = snapshot.delay_register;
+        self.sound_register = snapshot.sound_register;
+        self.keyboard_presses = snapshot.keyboard_presses;
+        self.is_waiting_for_input = snapshot.is_waiting_for_input;
+        self.pressed_key = snapshot.pressed_key;
+        self.rpl = snapshot.rpl;
+        self.exited = snapshot.exited;
+        self.selected_planes = snapshot.selected_planes;
+        self.audio_pattern = snapshot.audio_pattern;
+        self.pitch = snapshot.pitch;
+        self.rng = snapshot.rng;
+    }
 
-        // Using variables to make it more readable
-        // nnn or addr - A 12-bit value, the lowest 12 bits of the instruction
-        // n or nibble - A 4-bit value, the lowest 4 bits of the instruction
-        // x - A 4-bit value, the lower 4 bits of the high byte of the instruction
-        // y - A 4-bit value, the upper 4 bits of the low byte of the instruction
-        // kk or byte - An 8-bit value, the lowest 8 bits of the instruction
-        let nnn = (opcode & 0x0FFF) as usize;
-        let n = nibbles.3 as usize;
-        let x = nibbles.1 as usize;
-        let y = nibbles.2 as usize;
-        let kk = (opcode & 0x00FF) as u8;
+    /// Executes one frame worth of instructions, then ticks the delay and
+    /// sound timers once.
+    ///
+    /// While the debugger has the machine paused nothing runs and the timers
+    /// stand still, apart from a single instruction when one has been
+    /// requested. Hitting a breakpoint ends the frame early.
+    ///
+    /// A movie being recorded takes down the keys for the frame, and one being
+    /// played back replaces them.
+    pub fn run_frame(&mut self) -> Result<(), Chip8Error> {
+        // Paused frames don't run anything, so movies leave them out
+        let paused = self.debugger.is_paused();
+        if !paused {
+            self.movie_before_frame();
+        }
+        self.run_cycles()?;
+        if !paused {
+            self.movie_after_frame();
+        }
+        Ok(())
+    }
+
+    fn run_cycles(&mut self) -> Result<(), Chip8Error> {
+        for _ in 0..self.cycles_per_frame {
+            if self.debugger.is_paused() {
+                if self.debugger.take_single_step() {
+                    self.step()?;
+                    let registers = debugger::registers(self);
+                    self.debugger.report_stop(registers);
+                }
+                return Ok(());
+            }
+            // The debugger needs to look at the machine while deciding
+            let mut debugger = std::mem::take(&mut self.debugger);
+            let stop = debugger.check(self);
+            self.debugger = debugger;
+            if stop {
+                return Ok(());
+            }
+            self.step()?;
+            // Watchpoints stop the program once the instruction that set them
+            // off has finished, so the report can show what it changed
+            if self.debugger.has_watch_hits() {
+                self.debugger.pause();
+                let registers = debugger::registers(self);
+                self.debugger.report_stop(registers);
+                return Ok(());
+            }
+        }
+        // The timers keep counting down while Fx0A waits for a key
+        if self.delay_register > 0 {
+            self.delay_register -= 1;
+        }
+        if self.sound_register > 0 {
+            self.sound_register -= 1;
+        }
+        Ok(())
+    }
+
+    /// Fetches and executes a single instruction.
+    ///
+    /// On error the machine is left as it was before the faulting
+    /// instruction, with the program counter still pointing at it.
+    pub fn step(&mut self) -> Result<(), Chip8Error> {
+        if self.exited {
+            return Ok(());
+        }
+
+        if self.pc + INSTRUCTION_SIZE > self.ram.len() {
+            return Err(Chip8Error::PcOutOfRange { pc: self.pc });
+        }
+
+        // Get the current opcode
+        let opcode = self.get_current_opcode();
+        self.watch(Access::Execute, self.pc..self.pc + INSTRUCTION_SIZE, None);
 
-        match nibbles {
-            // 00EE - RET
-            (0x0, 0x0, 0xE, 0xE) => self.op_00ee(),
+        // Run the opcode
+        self.run_opcode(opcode)
+    }
 
-            // 00E0 - CLS
-            (0x0, 0x0, 0xE, 0x0) => self.op_00e0(),
+    pub fn display(&self) -> &Framebuffer {
+        &self.display
+    }
 
-            // 0nnn - SYS addr
-            (0x0, _, _, _) => (),
+    /// True once a SUPER-CHIP program has run 00FD.
+    pub fn has_exited(&self) -> bool {
+        self.exited
+    }
 
-            // 1nnn - JP addr
-            (0x1, _, _, _) => self.op_1nnn(nnn),
+    pub fn platform(&self) -> Platform {
+        self.platform
+    }
 
-            // 2nnn - CALL addr
-            (0x2, _, _, _) => self.op_2nnn(nnn),
+    /// What the speaker should currently be playing.
+    pub fn sound(&self) -> Sound {
+        Sound {
+            playing: self.sound_register > 0,
+            pattern: self.audio_pattern,
+            pitch: self.pitch,
+        }
+    }
 
-            // 3xkk - SE Vx, byte
-            (0x3, _, _, _) => self.op_3xkk(x, kk),
+    /// The registers and settings a frontend shows next to the screen.
+    pub fn status(&self) -> Status {
+        Status {
+            pc: self.pc,
+            delay_timer: self.delay_register,
+            sound_timer: self.sound_register,
+            cycles_per_frame: self.cycles_per_frame,
+            paused: self.debugger.is_paused(),
+        }
+    }
 
-            // 4xkk - SNE Vx, byte
-            (0x4, _, _, _) => self.op_4xkk(x, kk),
+    /// The XO-CHIP audio pattern, or `None` if the program has not loaded one
+    /// and a plain tone should be played instead.
+    pub fn audio_pattern(&self) -> Option<&[u8; AUDIO_PATTERN_SIZE]> {
+        self.audio_pattern.as_ref()
+    }
 
-            // 5xy0 - SE Vx, Vy
-            (0x5, _, _, 0x0) => self.op_5xy0(x, y),
+    /// The XO-CHIP pitch register. The audio pattern is played back at
+    /// 4000 * 2^((pitch - 64) / 48) bits per second.
+    pub fn pitch(&self) -> u8 {
+        self.pitch
+    }
 
-            // 6xkk - LD Vx, byte
-            (0x6, _, _, _) => self.op_6xkk(x, kk),
+    pub fn ram(&self) -> &[u8] {
+        &self.ram
+    }
 
-            // 7xkk - ADD Vx, byte
-            (0x7, _, _, _) => self.op_7xkk(x, kk),
+    pub fn v(&self) -> &[u8; REGISTER_COUNT] {
+        &self.v
+    }
 
-            // 8xy0 - LD Vx, Vy
-            (0x8, _, _, 0x0) => self.op_8xy0(x, y),
+    pub fn i_register(&self) -> usize {
+        self.i_register
+    }
 
-            // 8xy1 - OR Vx, Vy
-            (0x8, _, _, 0x1) => self.op_8xy1(x, y),
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
 
-            // 8xy2 - AND Vx, Vy
-            (0x8, _, _, 0x2) => self.op_8xy2(x, y),
+    pub fn sp(&self) -> usize {
+        self.sp
+    }
 
-            // 8xy3 - XOR Vx, Vy
-            (0x8, _, _, 0x3) => self.op_8xy3(x, y),
+    pub fn stack(&self) -> &[usize; STACK_SIZE] {
+        &self.stack
+    }
 
-            // 8xy4 - ADD Vx, Vy
-            (0x8, _, _, 0x4) => self.op_8xy4(x, y),
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_register
+    }
 
-            // 8xy5 - SUB Vx, Vy
-            (0x8, _, _, 0x5) => self.op_8xy5(x, y),
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_register
+    }
 
-            // 8xy6 - SHR Vx {, Vy}
-            (0x8, _, _, 0x6) => self.op_8xy6(x),
+    pub fn keys(&self) -> &[bool; KEYBOARD_SIZE] {
+        &self.keyboard_presses
+    }
 
-            // 8xy7 - SUBN Vx, Vy
-            (0x8, _, _, 0x7) => self.op_8xy7(x, y),
+    /// Sets which of the 16 keys are held down, as seen by Ex9E, ExA1 and Fx0A.
+    pub fn set_keys(&mut self, keys: [bool; KEYBOARD_SIZE]) {
+        self.keyboard_presses = keys;
+    }
 
-            // 8xyE - SHL Vx {, Vy}
-            (0x8, _, _, 0xE) => self.op_8xye(x),
+    /// How many instructions `run_frame` executes per 60Hz frame.
+    pub fn cycles_per_frame(&self) -> u32 {
+        self.cycles_per_frame
+    }
 
-            // 9xy0 - SNE Vx, Vy
-            (0x9, _, _, 0x0) => self.op_9xy0(x, y),
+    pub fn set_cycles_per_frame(&mut self, cycles: u32) {
+        self.cycles_per_frame = cycles.clamp(1, MAX_CYCLES_PER_FRAME);
+    }
 
-            // Annn - LD I, addr
-            (0xA, _, _, _) => self.op_annn(nnn),
+    /// Reseeds the random number generator used by Cxkk, making runs repeatable.
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
 
-            // Bnnn - JP V0, addr
-            (0xB, _, _, _) => self.op_bnnn(nnn),
+    /// The seed of the random number generator. Unless `seed_rng` was called it
+    /// was picked at random, and passing it to `seed_rng` repeats the run.
+    pub fn seed(&self) -> u64 {
+        self.rng.seed()
+    }
 
-            // Cxkk - RND Vx, byte
-            (0xC, _, _, _) => self.op_cxkk(x, kk),
+    /// Identifies the loaded ROM. See `movie::rom_hash`.
+    pub fn rom_hash(&self) -> u64 {
+        self.rom_hash
+    }
 
-            // Dxyn - DRW Vx, Vy, nibble
-            (0xD, _, _, _) => self.op_dxyn(x, y, n),
+    pub(crate) fn movie_state(&self) -> Option<&MovieState> {
+        self.movie.as_ref()
+    }
 
-            // Ex9E - SKP Vx
-            (0xE, _, 0x9, 0xE) => self.op_ex9e(x),
+    pub(crate) fn movie_state_mut(&mut self) -> &mut Option<MovieState> {
+        &mut self.movie
+    }
 
-            // ExA1 - SKNP Vx
-            (0xE, _, 0xA, 0x1) => self.op_exa1(x),
+    pub fn debugger(&self) -> &Debugger {
+        &self.debugger
+    }
 
-            // Fx07 - LD Vx, DT
-            (0xF, _, 0x0, 0x7) => self.op_fx07(x),
+    pub fn debugger_mut(&mut self) -> &mut Debugger {
+        &mut self.debugger
+    }
 
-            // Fx0A - LD Vx, K
-            (0xF, _, 0x0, 0xA) => self.op_fx0a(x),
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
 
-            // Fx15 - LD DT, Vx
-            (0xF, _, 0x1, 0x5) => self.op_fx15(x),
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
 
-            // Fx18 - LD ST, Vx
-            (0xF, _, 0x1, 0x8) => self.op_fx18(x),
+    fn get_current_opcode(&self) -> u16 {
+        (self.ram[self.pc] as u16) << 8 | (self.ram[self.pc + 1] as u16)
+    }
 
-            // Fx1E - ADD I, Vx
-            (0xF, _, 0x1, 0xE) => self.op_fx1e(x),
+    // Lets the debugger's watchpoints know the current instruction is
+    // accessing `range`. Writes pass the bytes about to be stored.
+    fn watch(&mut self, access: Access, range: Range<usize>, new: Option<&[u8]>) {
+        if self.debugger.is_watching() {
+            let by = Accessor {
+                pc: self.pc,
+                opcode: self.get_current_opcode(),
+                platform: self.platform,
+            };
+            let old = &self.ram[range.clone()];
+            self.debugger
+                .record_access(access, &by, range.start, old, new);
+        }
+    }
 
-            // Fx29 - LD F, Vx
-            (0xF, _, 0x2, 0x9) => self.op_fx29(x),
+    // Stores `bytes` starting at `address`, which must already have been
+    // checked with memory_range
+    fn write_memory(&mut self, address: usize, bytes: &[u8]) {
+        let range = address..address + bytes.len();
+        self.watch(Access::Write, range.clone(), Some(bytes));
+        self.ram[range].copy_from_slice(bytes);
+    }
 
-            // Fx33 - LD B, Vx
-            (0xF, _, 0x3, 0x3) => self.op_fx33(x),
+    // Returns the memory range of `len` bytes starting at `address`, or an
+    // error blaming the current instruction if any of it is out of bounds.
+    // Every instruction that touches memory through I goes through here.
+    fn memory_range(&self, address: usize, len: usize) -> Result<Range<usize>, Chip8Error> {
+        if address + len > self.ram.len() {
+            return Err(Chip8Error::MemoryOutOfBounds {
+                pc: self.pc,
+                opcode: self.get_current_opcode(),
+                address: address.max(self.ram.len()),
+            });
+        }
+        Ok(address..address + len)
+    }
 
-            // Fx55 - LD [I], Vx
-            (0xF, _, 0x5, 0x5) => self.op_fx55(x),
+    // Advances past the current instruction, and past the next one as well if
+    // `condition` holds. XO-CHIP's F000 nnnn is four bytes long, so skipping it
+    // has to jump over both halves.
+    fn skip_if(&mut self, condition: bool) {
+        self.pc += INSTRUCTION_SIZE;
+        if condition {
+            let next = self.ram.get(self.pc..self.pc + INSTRUCTION_SIZE);
+            let is_long_load = self.platform == Platform::XoChip && next == Some(&[0xF0, 0x00]);
+            self.pc += if is_long_load {
+                2 * INSTRUCTION_SIZE
+            } else {
+                INSTRUCTION_SIZE
+            };
+        }
+    }
 
-            // Fx65 - LD Vx, [I]
-            (0xF, _, 0x6, 0x5) => self.op_fx65(x),
+    fn run_opcode(&mut self, opcode: u16) -> Result<(), Chip8Error> {
+        // The meaning of each opcode is in instruction.rs, shared with the
+        // disassembler
+        let Some(instruction) = Instruction::decode(opcode, self.platform) else {
+            return Err(Chip8Error::UnknownOpcode {
+                pc: self.pc,
+                opcode,
+            });
+        };
 
-            _ => (),
+        match instruction {
+            Instruction::Ret => self.op_00ee()?,
+            Instruction::Cls => self.op_00e0(),
+            Instruction::Scd(n) => self.op_00cn(n),
+            Instruction::Scu(n) => self.op_00dn(n),
+            Instruction::Scr => self.op_00fb(),
+            Instruction::Scl => self.op_00fc(),
+            Instruction::Exit => self.op_00fd(),
+            Instruction::Low => self.op_00fe(),
+            Instruction::High => self.op_00ff(),
+            Instruction::Sys(_) => self.op_0nnn(),
+            Instruction::Jp(nnn) => self.op_1nnn(nnn),
+            Instruction::Call(nnn) => self.op_2nnn(nnn)?,
+            Instruction::SeByte(x, kk) => self.op_3xkk(x, kk),
+            Instruction::SneByte(x, kk) => self.op_4xkk(x, kk),
+            Instruction::SeReg(x, y) => self.op_5xy0(x, y),
+            Instruction::Save(x, y) => self.op_5xy2(x, y)?,
+            Instruction::Load(x, y) => self.op_5xy3(x, y)?,
+            Instruction::LdByte(x, kk) => self.op_6xkk(x, kk),
+            Instruction::AddByte(x, kk) => self.op_7xkk(x, kk),
+            Instruction::LdReg(x, y) => self.op_8xy0(x, y),
+            Instruction::Or(x, y) => self.op_8xy1(x, y),
+            Instruction::And(x, y) => self.op_8xy2(x, y),
+            Instruction::Xor(x, y) => self.op_8xy3(x, y),
+            Instruction::AddReg(x, y) => self.op_8xy4(x, y),
+            Instruction::Sub(x, y) => self.op_8xy5(x, y),
+            Instruction::Shr(x, y) => self.op_8xy6(x, y),
+            Instruction::Subn(x, y) => self.op_8xy7(x, y),
+            Instruction::Shl(x, y) => self.op_8xye(x, y),
+            Instruction::SneReg(x, y) => self.op_9xy0(x, y),
+            Instruction::LdI(nnn) => self.op_annn(nnn),
+            Instruction::JpV0(x, nnn) => self.op_bnnn(x, nnn),
+            Instruction::Rnd(x, kk) => self.op_cxkk(x, kk),
+            Instruction::Drw(x, y, n) => self.op_dxyn(x, y, n)?,
+            Instruction::Skp(x) => self.op_ex9e(x),
+            Instruction::Sknp(x) => self.op_exa1(x),
+            Instruction::LdILong => self.op_f000()?,
+            Instruction::Plane(n) => self.op_fn01(n),
+            Instruction::Audio => self.op_f002()?,
+            Instruction::LdVxDt(x) => self.op_fx07(x),
+            Instruction::LdVxK(x) => self.op_fx0a(x),
+            Instruction::LdDtVx(x) => self.op_fx15(x),
+            Instruction::LdStVx(x) => self.op_fx18(x),
+            Instruction::AddIVx(x) => self.op_fx1e(x),
+            Instruction::LdF(x) => self.op_fx29(x),
+            Instruction::LdHf(x) => self.op_fx30(x),
+            Instruction::LdB(x) => self.op_fx33(x)?,
+            Instruction::Pitch(x) => self.op_fx3a(x),
+            Instruction::LdIVx(x) => self.op_fx55(x)?,
+            Instruction::LdVxI(x) => self.op_fx65(x)?,
+            Instruction::LdRVx(x) => self.op_fx75(x),
+            Instruction::LdVxR(x) => self.op_fx85(x),
         }
+        Ok(())
     }
 
     /*  00E0 - CLS
@@ -276,11 +717,87 @@ impl Processor {
     */
     fn op_00e0(&mut self) {
         debug!("CLS");
-        for row in 0..DISPLAY_HEIGHT {
-            for pixel in 0..DISPLAY_WIDTH {
-                self.display[row][pixel] = 0;
-            }
-        }
+        self.display.clear(self.selected_planes);
+        self.pc += INSTRUCTION_SIZE;
+    }
+
+    /*  0nnn - SYS addr
+        Jump to a machine code routine at nnn.
+
+        This instruction is only used on the old computers on which Chip-8 was
+        originally implemented. It is ignored by modern interpreters.
+    */
+    fn op_0nnn(&mut self) {
+        debug!("SYS");
+        self.pc += INSTRUCTION_SIZE;
+    }
+
+    /*  00Cn - SCD nibble
+        Scroll the display down by n pixels. (SUPER-CHIP)
+    */
+    fn op_00cn(&mut self, n: usize) {
+        debug!("SCD");
+        self.display.scroll_down(n, self.selected_planes);
+        self.pc += INSTRUCTION_SIZE;
+    }
+
+    /*  00Dn - SCU nibble
+        Scroll the display up by n pixels. (XO-CHIP)
+    */
+    fn op_00dn(&mut self, n: usize) {
+        debug!("SCU");
+        self.display.scroll_up(n, self.selected_planes);
+        self.pc += INSTRUCTION_SIZE;
+    }
+
+    /*  00FB - SCR
+        Scroll the display right by 4 pixels. (SUPER-CHIP)
+    */
+    fn op_00fb(&mut self) {
+        debug!("SCR");
+        self.display.scroll_right(4, self.selected_planes);
+        self.pc += INSTRUCTION_SIZE;
+    }
+
+    /*  00FC - SCL
+        Scroll the display left by 4 pixels. (SUPER-CHIP)
+    */
+    fn op_00fc(&mut self) {
+        debug!("SCL");
+        self.display.scroll_left(4, self.selected_planes);
+        self.pc += INSTRUCTION_SIZE;
+    }
+
+    /*  00FD - EXIT
+        Exit the interpreter. (SUPER-CHIP)
+
+        The program counter is left on the exit instruction and
+        no further instructions are executed.
+    */
+    fn op_00fd(&mut self) {
+        debug!("EXIT");
+        self.exited = true;
+    }
+
+    /*  00FE - LOW
+        Switch to the 64x32 low resolution mode. (SUPER-CHIP)
+
+        Changing resolution clears the display.
+    */
+    fn op_00fe(&mut self) {
+        debug!("LOW");
+        self.display.resize(LORES_WIDTH, LORES_HEIGHT);
+        self.pc += INSTRUCTION_SIZE;
+    }
+
+    /*  00FF - HIGH
+        Switch to the 128x64 high resolution mode. (SUPER-CHIP)
+
+        Changing resolution clears the display.
+    */
+    fn op_00ff(&mut self) {
+        debug!("HIGH");
+        self.display.resize(HIRES_WIDTH, HIRES_HEIGHT);
         self.pc += INSTRUCTION_SIZE;
     }
 
@@ -290,11 +807,18 @@ impl Processor {
         The interpreter sets the program counter to the address at the top of the stack,
         then subtracts 1 from the stack pointer.
     */
-    fn op_00ee(&mut self) {
+    fn op_00ee(&mut self) -> Result<(), Chip8Error> {
         debug!("RET");
+        if self.sp == 0 {
+            return Err(Chip8Error::StackUnderflow {
+                pc: self.pc,
+                opcode: self.get_current_opcode(),
+            });
+        }
         self.pc = self.stack[self.sp];
         self.sp -= 1;
         self.pc += INSTRUCTION_SIZE;
+        Ok(())
     }
 
     /*  1nnn - JP addr
@@ -313,11 +837,18 @@ impl Processor {
         The interpreter increments the stack pointer, then puts the current
         PC on the top of the stack. The PC is then set.
     */
-    fn op_2nnn(&mut self, nnn: usize) {
+    fn op_2nnn(&mut self, nnn: usize) -> Result<(), Chip8Error> {
         debug!("2nnn");
+        if self.sp + 1 >= STACK_SIZE {
+            return Err(Chip8Error::StackOverflow {
+                pc: self.pc,
+                opcode: self.get_current_opcode(),
+            });
+        }
         self.sp += 1;
         self.stack[self.sp] = self.pc;
         self.pc = nnn;
+        Ok(())
     }
 
     /*  3xkk - SE Vx, byte
@@ -328,11 +859,7 @@ impl Processor {
     */
     fn op_3xkk(&mut self, x: usize, kk: u8) {
         debug!("3xkk");
-        self.pc += if self.v[x] == kk {
-            2 * INSTRUCTION_SIZE
-        } else {
-            INSTRUCTION_SIZE
-        }
+        self.skip_if(self.v[x] == kk);
     }
 
     /*  4xkk - SNE Vx, byte
@@ -343,11 +870,7 @@ impl Processor {
     */
     fn op_4xkk(&mut self, x: usize, kk: u8) {
         debug!("4xkk");
-        self.pc += if self.v[x] != kk {
-            2 * INSTRUCTION_SIZE
-        } else {
-            INSTRUCTION_SIZE
-        }
+        self.skip_if(self.v[x] != kk);
     }
 
     /*  5xy0 - SE Vx, Vy
@@ -357,11 +880,41 @@ impl Processor {
     */
     fn op_5xy0(&mut self, x: usize, y: usize) {
         debug!("5xy0");
-        self.pc += if self.v[x] == self.v[y] {
-            2 * INSTRUCTION_SIZE
-        } else {
-            INSTRUCTION_SIZE
+        self.skip_if(self.v[x] == self.v[y]);
+    }
+
+    /*  5xy2 - SAVE Vx - Vy
+        Store registers Vx through Vy in memory starting at location I. (XO-CHIP)
+
+        If x is greater than y the registers are stored in reverse order.
+        I is not modified.
+    */
+    fn op_5xy2(&mut self, x: usize, y: usize) -> Result<(), Chip8Error> {
+        debug!("5xy2");
+        let range = self.memory_range(self.i_register, x.abs_diff(y) + 1)?;
+        let values: Vec<u8> = register_range(x, y)
+            .map(|register| self.v[register])
+            .collect();
+        self.write_memory(range.start, &values);
+        self.pc += INSTRUCTION_SIZE;
+        Ok(())
+    }
+
+    /*  5xy3 - LOAD Vx - Vy
+        Read registers Vx through Vy from memory starting at location I. (XO-CHIP)
+
+        If x is greater than y the registers are loaded in reverse order.
+        I is not modified.
+    */
+    fn op_5xy3(&mut self, x: usize, y: usize) -> Result<(), Chip8Error> {
+        debug!("5xy3");
+        let range = self.memory_range(self.i_register, x.abs_diff(y) + 1)?;
+        self.watch(Access::Read, range.clone(), None);
+        for (address, register) in range.zip(register_range(x, y)) {
+            self.v[register] = self.ram[address];
         }
+        self.pc += INSTRUCTION_SIZE;
+        Ok(())
     }
 
     /*  6xkk - LD Vx, byte
@@ -407,6 +960,9 @@ impl Processor {
     fn op_8xy1(&mut self, x: usize, y: usize) {
         debug!("8xy1");
         self.v[x] |= self.v[y];
+        if self.quirks.logic_resets_vf {
+            self.v[0xF] = 0;
+        }
         self.pc += INSTRUCTION_SIZE;
     }
 
@@ -419,6 +975,9 @@ impl Processor {
     fn op_8xy2(&mut self, x: usize, y: usize) {
         debug!("8xy2");
         self.v[x] &= self.v[y];
+        if self.quirks.logic_resets_vf {
+            self.v[0xF] = 0;
+        }
         self.pc += INSTRUCTION_SIZE;
     }
 
@@ -431,6 +990,9 @@ impl Processor {
     fn op_8xy3(&mut self, x: usize, y: usize) {
         debug!("8xy3");
         self.v[x] ^= self.v[y];
+        if self.quirks.logic_resets_vf {
+            self.v[0xF] = 0;
+        }
         self.pc += INSTRUCTION_SIZE;
     }
 
@@ -440,33 +1002,32 @@ impl Processor {
         The values of Vx and Vy are added together. If the result is greater
         than 8 bits (i.e., > 255,) VF is set to 1, otherwise 0.
         Only the lowest 8 bits of the result are kept, and stored in Vx.
+
+        Like the other arithmetic instructions, the flag is written after the
+        result, so it wins when x is F.
     */
     fn op_8xy4(&mut self, x: usize, y: usize) {
         debug!("8xy4");
-        self.v[x] = match self.v[x].overflowing_add(self.v[y]) {
-            (sum, true) => {
-                // Overflow occurred
-                self.v[0xF] = 1;
-                sum
-            }
-            (sum, false) => {
-                self.v[0xF] = 0;
-                sum
-            }
-        };
+        let (sum, carry) = self.v[x].overflowing_add(self.v[y]);
+        self.v[x] = sum;
+        self.v[0xF] = carry as u8;
         self.pc += INSTRUCTION_SIZE;
     }
 
     /*  8xy5 - SUB Vx, Vy
         Set Vx = Vx - Vy, set VF = NOT borrow.
 
-        If Vx > Vy, then VF is set to 1, otherwise 0.
+        If Vx >= Vy, then VF is set to 1, otherwise 0.
         Then Vy is subtracted from Vx, and the results stored in Vx.
+
+        Cowgod's reference says Vx > Vy, but there is no borrow when the two
+        are equal and the COSMAC VIP sets VF to 1.
     */
     fn op_8xy5(&mut self, x: usize, y: usize) {
         debug!("8xy5");
-        self.v[0xF] = if self.v[x] > self.v[y] { 1 } else { 0 };
-        self.v[x] = self.v[x].wrapping_sub(self.v[y]);
+        let (difference, borrow) = self.v[x].overflowing_sub(self.v[y]);
+        self.v[x] = difference;
+        self.v[0xF] = !borrow as u8;
         self.pc += INSTRUCTION_SIZE;
     }
 
@@ -475,24 +1036,31 @@ impl Processor {
 
         If the least-significant bit of Vx is 1, then VF is set to 1,
         otherwise 0. Then Vx is divided by 2.
+
+        On the COSMAC VIP Vy is copied into Vx first (see Quirks::shift_uses_vy).
     */
-    fn op_8xy6(&mut self, x: usize) {
+    fn op_8xy6(&mut self, x: usize, y: usize) {
         debug!("8xy6");
-        self.v[0xF] = self.v[x] & 1u8;
+        if self.quirks.shift_uses_vy {
+            self.v[x] = self.v[y];
+        }
+        let shifted_out = self.v[x] & 1;
         self.v[x] >>= 1;
+        self.v[0xF] = shifted_out;
         self.pc += INSTRUCTION_SIZE;
     }
 
     /*  8xy7 - SUBN Vx, Vy
         Set Vx = Vy - Vx, set VF = NOT borrow.
 
-        If Vy > Vx, then VF is set to 1, otherwise 0.
+        If Vy >= Vx, then VF is set to 1, otherwise 0.
         Then Vx is subtracted from Vy, and the results stored in Vx.
     */
     fn op_8xy7(&mut self, x: usize, y: usize) {
         debug!("8xy7");
-        self.v[0xF] = if self.v[y] > self.v[x] { 1 } else { 0 };
-        self.v[x] = self.v[y].wrapping_sub(self.v[x]);
+        let (difference, borrow) = self.v[y].overflowing_sub(self.v[x]);
+        self.v[x] = difference;
+        self.v[0xF] = !borrow as u8;
         self.pc += INSTRUCTION_SIZE;
     }
 
@@ -501,11 +1069,17 @@ impl Processor {
 
         If the most-significant bit of Vx is 1, then VF is set to 1,
         otherwise to 0. Then Vx is multiplied by 2.
+
+        On the COSMAC VIP Vy is copied into Vx first (see Quirks::shift_uses_vy).
     */
-    fn op_8xye(&mut self, x: usize) {
+    fn op_8xye(&mut self, x: usize, y: usize) {
         debug!("8xye");
-        self.v[0xF] = self.v[x] & 1u8;
+        if self.quirks.shift_uses_vy {
+            self.v[x] = self.v[y];
+        }
+        let shifted_out = self.v[x] >> 7;
         self.v[x] <<= 1;
+        self.v[0xF] = shifted_out;
         self.pc += INSTRUCTION_SIZE;
     }
 
@@ -517,11 +1091,7 @@ impl Processor {
     */
     fn op_9xy0(&mut self, x: usize, y: usize) {
         debug!("9xy0");
-        self.pc += if self.v[x] != self.v[y] {
-            2 * INSTRUCTION_SIZE
-        } else {
-            INSTRUCTION_SIZE
-        };
+        self.skip_if(self.v[x] != self.v[y]);
     }
 
     /*  Annn - LD I, addr
@@ -539,10 +1109,18 @@ impl Processor {
         Jump to location nnn + V0.
 
         The program counter is set to nnn plus the value of V0.
+
+        CHIP-48 and SUPER-CHIP read this as Bxnn and add Vx instead
+        (see Quirks::jump_uses_vx).
     */
-    fn op_bnnn(&mut self, nnn: usize) {
+    fn op_bnnn(&mut self, x: usize, nnn: usize) {
         debug!("bnnn");
-        self.pc = nnn + self.v[0x0] as usize;
+        let offset = if self.quirks.jump_uses_vx {
+            self.v[x]
+        } else {
+            self.v[0x0]
+        };
+        self.pc = nnn + offset as usize;
     }
 
     /*  Cxkk - RND Vx, byte
@@ -553,7 +1131,7 @@ impl Processor {
     */
     fn op_cxkk(&mut self, x: usize, kk: u8) {
         debug!("cxkk");
-        let random_byte: u8 = rand::thread_rng().gen();
+        let random_byte = self.rng.next_u8();
         self.v[x] = kk & random_byte;
         self.pc += INSTRUCTION_SIZE;
     }
@@ -567,24 +1145,80 @@ impl Processor {
         VF is set to 1, otherwise it is set to 0. If the sprite is positioned so part of
         it is outside the coordinates of the display, it wraps around to the opposite
         side of the screen.
+
+        Most interpreters other than Cowgod's only wrap the starting coordinates and
+        cut off the rest of the sprite at the edge (see Quirks::clip_sprites).
+
+        On SUPER-CHIP, Dxy0 draws a 16x16 sprite made of 32 bytes, two per row.
+
+        On XO-CHIP the sprite is drawn to every plane selected by Fn01. When both
+        planes are selected the data for the second plane follows the first.
     */
-    fn op_dxyn(&mut self, x: usize, y: usize, n: usize) {
+    fn op_dxyn(&mut self, x: usize, y: usize, n: usize) -> Result<(), Chip8Error> {
         debug!("dxyn");
+        let (sprite_width, rows) = if n == 0 && self.platform.has_schip_instructions() {
+            (16, 16)
+        } else {
+            (8, n)
+        };
+        let sprite_size = rows * sprite_width / 8;
+        let planes = self.selected_planes.count_ones() as usize;
+        let range = self.memory_range(self.i_register, sprite_size * planes)?;
+        self.watch(Access::Read, range, None);
+
         self.v[0xF] = 0;
-        for row in 0..n {
-            let y_index = (self.v[y] as usize + row) % DISPLAY_HEIGHT;
-            for col in 0..8 {
-                // XOR onto the screen, if a pixel became 0 set VF to 1
+        let mut address = self.i_register;
+        for plane in 0..PLANE_COUNT {
+            let plane = 1 << plane;
+            if self.selected_planes & plane == 0 {
+                continue;
+            }
+            if self.draw_sprite(address, x, y, sprite_width, rows, plane) {
+                self.v[0xF] = 1;
+            }
+            address += sprite_size;
+        }
+        self.pc += INSTRUCTION_SIZE;
+        Ok(())
+    }
+
+    // Draws a single plane of a sprite, returning true if any pixel was erased
+    fn draw_sprite(
+        &mut self,
+        address: usize,
+        x: usize,
+        y: usize,
+        sprite_width: usize,
+        rows: usize,
+        plane: u8,
+    ) -> bool {
+        let (width, height) = (self.display.width(), self.display.height());
+        let bytes_per_row = sprite_width / 8;
+        let x_start = self.v[x] as usize % width;
+        let y_start = self.v[y] as usize % height;
+        let mut collision = false;
+        for row in 0..rows {
+            if self.quirks.clip_sprites && y_start + row >= height {
+                break;
+            }
+            let y_index = (y_start + row) % height;
+            for col in 0..sprite_width {
+                if self.quirks.clip_sprites && x_start + col >= width {
+                    break;
+                }
+                let byte = self.ram[address + row * bytes_per_row + col / 8];
+                if (byte >> (7 - col % 8)) & 1 == 0 {
+                    continue;
+                }
+                // XOR onto the screen, if a pixel was erased report a collision
                 // Wrapping around the screen if required
-                let x_index = (self.v[x] as usize + col) % DISPLAY_WIDTH;
-                let pixel = (self.ram[self.i_register + row] >> (7 - col)) & 1;
-                self.display[y_index][x_index] ^= pixel;
-                if self.display[y_index][x_index] == 0 {
-                    self.v[0xF] = 1;
+                let x_index = (x_start + col) % width;
+                if self.display.xor(x_index, y_index, plane) {
+                    collision = true;
                 }
             }
         }
-        self.pc += INSTRUCTION_SIZE;
+        collision
     }
 
     /*  Ex9E - SKP Vx
@@ -595,11 +1229,7 @@ impl Processor {
     */
     fn op_ex9e(&mut self, x: usize) {
         debug!("ex9e");
-        self.pc += if self.keyboard_presses[self.v[x] as usize] == true {
-            2 * INSTRUCTION_SIZE
-        } else {
-            INSTRUCTION_SIZE
-        };
+        self.skip_if(self.keyboard_presses[(self.v[x] & 0xF) as usize]);
     }
 
     /*  ExA1 - SKNP Vx
@@ -610,11 +1240,48 @@ impl Processor {
     */
     fn op_exa1(&mut self, x: usize) {
         debug!("exa1");
-        self.pc += if self.keyboard_presses[self.v[x] as usize] == false {
-            2 * INSTRUCTION_SIZE
-        } else {
-            INSTRUCTION_SIZE
-        };
+        self.skip_if(!self.keyboard_presses[(self.v[x] & 0xF) as usize]);
+    }
+
+    /*  F000 nnnn - LD I, long addr
+        Set I = nnnn. (XO-CHIP)
+
+        The address is the 16-bit word following the instruction,
+        so this instruction is four bytes long.
+    */
+    fn op_f000(&mut self) -> Result<(), Chip8Error> {
+        debug!("f000");
+        let range = self.memory_range(self.pc + INSTRUCTION_SIZE, 2)?;
+        self.watch(Access::Execute, range.clone(), None);
+        self.i_register =
+            (self.ram[range.start] as usize) << 8 | self.ram[range.start + 1] as usize;
+        self.pc += 2 * INSTRUCTION_SIZE;
+        Ok(())
+    }
+
+    /*  Fn01 - PLANE n
+        Select the bitplanes drawn to by CLS, DRW and the scroll instructions. (XO-CHIP)
+
+        n is a bitmask, 0 selects no planes and 3 selects both.
+    */
+    fn op_fn01(&mut self, n: usize) {
+        debug!("fn01");
+        self.selected_planes = n as u8 & 0x3;
+        self.pc += INSTRUCTION_SIZE;
+    }
+
+    /*  F002 - AUDIO
+        Load the 16-byte audio pattern buffer from memory starting at I. (XO-CHIP)
+    */
+    fn op_f002(&mut self) -> Result<(), Chip8Error> {
+        debug!("f002");
+        let range = self.memory_range(self.i_register, AUDIO_PATTERN_SIZE)?;
+        self.watch(Access::Read, range.clone(), None);
+        let mut pattern = [0; AUDIO_PATTERN_SIZE];
+        pattern.copy_from_slice(&self.ram[range]);
+        self.audio_pattern = Some(pattern);
+        self.pc += INSTRUCTION_SIZE;
+        Ok(())
     }
 
     /*  Fx07 - LD Vx, DT
@@ -633,21 +1300,30 @@ impl Processor {
 
         All execution stops until a key is pressed,
         then the value of that key is stored in Vx.
+
+        Like the COSMAC VIP, the key is only taken once it is released, so a
+        key held down doesn't run straight through several waits.
     */
     fn op_fx0a(&mut self, x: usize) {
         debug!("fx0a");
 
-        // Check if an input was made
-        if self.is_waiting_for_input {
-            for (i, key) in self.keyboard_presses.iter().enumerate() {
-                if *key == true {
-                    self.is_waiting_for_input = false;
-                    self.v[x] = i as u8;
-                    self.pc += INSTRUCTION_SIZE;
-                }
-            }
-        } else {
+        if !self.is_waiting_for_input {
             self.is_waiting_for_input = true;
+            return;
+        }
+        match self.pressed_key {
+            // The lowest key, if several go down together
+            None => {
+                let key = self.keyboard_presses.iter().position(|&down| down);
+                self.pressed_key = key.map(|key| key as u8);
+            }
+            Some(key) if !self.keyboard_presses[key as usize] => {
+                self.is_waiting_for_input = false;
+                self.pressed_key = None;
+                self.v[x] = key;
+                self.pc += INSTRUCTION_SIZE;
+            }
+            Some(_) => (),
         }
     }
 
@@ -692,7 +1368,18 @@ impl Processor {
     */
     fn op_fx29(&mut self, x: usize) {
         debug!("fx29");
-        self.i_register = (self.v[x] * 5) as usize;
+        self.i_register = (self.v[x] & 0xF) as usize * 5;
+        self.pc += INSTRUCTION_SIZE;
+    }
+
+    /*  Fx30 - LD HF, Vx
+        Set I = location of the large sprite for digit Vx. (SUPER-CHIP)
+
+        The large font is 8x10, so each digit takes 10 bytes.
+    */
+    fn op_fx30(&mut self, x: usize) {
+        debug!("fx30");
+        self.i_register = BIG_FONT_ADDRESS + (self.v[x] & 0xF) as usize * 10;
         self.pc += INSTRUCTION_SIZE;
     }
 
@@ -703,11 +1390,21 @@ impl Processor {
         digit in memory at location in I, the tens digit at location I+1, and
         the ones digit at location I+2.
     */
-    fn op_fx33(&mut self, x: usize) {
+    fn op_fx33(&mut self, x: usize) -> Result<(), Chip8Error> {
         debug!("fx33");
-        self.ram[self.i_register] = self.v[x] / 100;
-        self.ram[self.i_register + 1] = (self.v[x] % 100) / 10;
-        self.ram[self.i_register + 2] = self.v[x] % 10;
+        let range = self.memory_range(self.i_register, 3)?;
+        let digits = [self.v[x] / 100, (self.v[x] % 100) / 10, self.v[x] % 10];
+        self.write_memory(range.start, &digits);
+        self.pc += INSTRUCTION_SIZE;
+        Ok(())
+    }
+
+    /*  Fx3A - PITCH Vx
+        Set the audio pattern playback rate to Vx. (XO-CHIP)
+    */
+    fn op_fx3a(&mut self, x: usize) {
+        debug!("fx3a");
+        self.pitch = self.v[x];
         self.pc += INSTRUCTION_SIZE;
     }
 
@@ -716,13 +1413,19 @@ impl Processor {
 
         The interpreter copies the values of registers V0 through Vx into
         memory, starting at the address in I.
+
+        The COSMAC VIP advances I as it goes (see Quirks::load_store_increments_i).
     */
-    fn op_fx55(&mut self, x: usize) {
+    fn op_fx55(&mut self, x: usize) -> Result<(), Chip8Error> {
         debug!("fx55");
-        for i in 0..(x + 1) {
-            self.ram[self.i_register + i] = self.v[i];
+        let range = self.memory_range(self.i_register, x + 1)?;
+        let values = self.v;
+        self.write_memory(range.start, &values[..=x]);
+        if self.quirks.load_store_increments_i {
+            self.i_register += x + 1;
         }
         self.pc += INSTRUCTION_SIZE;
+        Ok(())
     }
 
     /*  Fx65 - LD Vx, [I]
@@ -730,24 +1433,59 @@ impl Processor {
 
         The interpreter reads values from memory starting at location I
         into registers V0 through Vx.
+
+        The COSMAC VIP advances I as it goes (see Quirks::load_store_increments_i).
     */
-    fn op_fx65(&mut self, x: usize) {
+    fn op_fx65(&mut self, x: usize) -> Result<(), Chip8Error> {
         debug!("fx65");
-        for i in 0..(x + 1) {
-            self.v[i] = self.ram[self.i_register + i];
+        let range = self.memory_range(self.i_register, x + 1)?;
+        self.watch(Access::Read, range.clone(), None);
+        self.v[..=x].copy_from_slice(&self.ram[range]);
+        if self.quirks.load_store_increments_i {
+            self.i_register += x + 1;
         }
         self.pc += INSTRUCTION_SIZE;
+        Ok(())
     }
 
-    pub fn print_ram(&self, condensed: bool) {
-        println!("RAM:");
-        for i in 512..RAM {
-            if condensed {
-                print!("{:X?}, ", self.ram[i]);
-            } else {
-                print!("{}: {:X?}", i, self.ram[i]);
-            }
-        }
-        println!();
+    /*  Fx75 - LD R, Vx
+        Store registers V0 through Vx in the RPL user flags. (SUPER-CHIP)
+    */
+    fn op_fx75(&mut self, x: usize) {
+        debug!("fx75");
+        self.rpl[..=x].copy_from_slice(&self.v[..=x]);
+        self.pc += INSTRUCTION_SIZE;
+    }
+
+    /*  Fx85 - LD Vx, R
+        Read registers V0 through Vx from the RPL user flags. (SUPER-CHIP)
+    */
+    fn op_fx85(&mut self, x: usize) {
+        debug!("fx85");
+        self.v[..=x].copy_from_slice(&self.rpl[..=x]);
+        self.pc += INSTRUCTION_SIZE;
+    }
+}
+
+#[cfg(test)]
+mod tests;
+
+// Registers x through y inclusive, counting down if x is greater than y
+fn register_range(x: usize, y: usize) -> Box<dyn Iterator<Item = usize>> {
+    if x <= y {
+        Box::new(x..=y)
+    } else {
+        Box::new((y..=x).rev())
+    }
+}
+
+#[cfg(test)]
+impl Processor {
+    // A machine with `program` loaded at 0x200, for the tests of any module
+    pub(crate) fn with_program(platform: Platform, quirks: Quirks, program: &[u16]) -> Processor {
+        let mut processor = Processor::with_platform(platform, quirks);
+        let bytes: Vec<u8> = program.iter().flat_map(|op| op.to_be_bytes()).collect();
+        processor.load_program(&bytes).unwrap();
+        processor
     }
 }
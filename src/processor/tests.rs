@@ -0,0 +1,628 @@
+/*
+    Every test runs a few instructions on a fresh machine and checks the result.
+    Anything whose behaviour is the same everywhere is checked under every quirk
+    profile, and the quirky instructions are checked against what each profile
+    says they should do.
+*/
+
+use super::*;
+
+// Cowgod's behaviour, then each platform's preset
+fn profiles() -> Vec<(Platform, Quirks)> {
+    let mut profiles = vec![(Platform::CosmacVip, Quirks::default())];
+    profiles.extend(
+        Platform::ALL
+            .iter()
+            .map(|&platform| (platform, platform.quirks())),
+    );
+    profiles
+}
+
+// Runs one instruction per opcode in `program`
+fn run(platform: Platform, quirks: Quirks, program: &[u16]) -> Processor {
+    let mut processor = Processor::with_program(platform, quirks, program);
+    for _ in program {
+        processor.step().unwrap();
+    }
+    processor
+}
+
+// Runs `program` under every profile and hands each result to `check`
+fn each_profile(program: &[u16], check: impl Fn(&Processor, Quirks)) {
+    for (platform, quirks) in profiles() {
+        check(&run(platform, quirks, program), quirks);
+    }
+}
+
+fn lit_pixels(processor: &Processor) -> Vec<(usize, usize)> {
+    let width = processor.display().width();
+    (0..processor.display().pixels().len())
+        .filter(|&i| processor.display().pixels()[i] != 0)
+        .map(|i| (i % width, i / width))
+        .collect()
+}
+
+#[test]
+fn cls_clears_the_screen() {
+    // Draw the 0 glyph, then clear
+    each_profile(&[0xD005, 0x00E0], |p, _| {
+        assert!(lit_pixels(p).is_empty());
+        assert_eq!(p.pc(), 0x204);
+    });
+}
+
+#[test]
+fn sys_is_ignored() {
+    each_profile(&[0x0123], |p, _| assert_eq!(p.pc(), 0x202));
+}
+
+#[test]
+fn call_and_ret() {
+    // 0x200: CALL 0x206, 0x202: LD V1, 1, 0x204: JP 0x204, 0x206: LD V0, 7, 0x208: RET
+    let program = [0x2206, 0x6101, 0x1204, 0x6007, 0x00EE];
+    for (platform, quirks) in profiles() {
+        let mut p = Processor::with_program(platform, quirks, &program);
+        p.step().unwrap();
+        assert_eq!((p.pc(), p.sp(), p.stack()[1]), (0x206, 1, 0x200));
+        p.step().unwrap();
+        p.step().unwrap();
+        assert_eq!((p.pc(), p.sp(), p.v()[0]), (0x202, 0, 7));
+    }
+}
+
+#[test]
+fn ret_with_an_empty_stack_fails() {
+    let mut p = Processor::with_program(Platform::CosmacVip, Quirks::default(), &[0x00EE]);
+    assert!(matches!(
+        p.step(),
+        Err(Chip8Error::StackUnderflow {
+            pc: 0x200,
+            opcode: 0x00EE
+        })
+    ));
+    assert_eq!(p.pc(), 0x200);
+}
+
+#[test]
+fn call_with_a_full_stack_fails() {
+    // Calls itself forever
+    let mut p = Processor::with_program(Platform::CosmacVip, Quirks::default(), &[0x2200]);
+    for _ in 0..STACK_SIZE - 1 {
+        p.step().unwrap();
+    }
+    assert!(matches!(p.step(), Err(Chip8Error::StackOverflow { .. })));
+}
+
+#[test]
+fn jp_jumps() {
+    each_profile(&[0x1ABC], |p, _| assert_eq!(p.pc(), 0xABC));
+}
+
+#[test]
+fn se_and_sne_with_a_byte() {
+    each_profile(&[0x6005, 0x3005], |p, _| assert_eq!(p.pc(), 0x206));
+    each_profile(&[0x6005, 0x3006], |p, _| assert_eq!(p.pc(), 0x204));
+    each_profile(&[0x6005, 0x4005], |p, _| assert_eq!(p.pc(), 0x204));
+    each_profile(&[0x6005, 0x4006], |p, _| assert_eq!(p.pc(), 0x206));
+}
+
+#[test]
+fn se_and_sne_with_a_register() {
+    each_profile(&[0x6005, 0x6105, 0x5010], |p, _| assert_eq!(p.pc(), 0x208));
+    each_profile(&[0x6005, 0x6106, 0x5010], |p, _| assert_eq!(p.pc(), 0x206));
+    each_profile(&[0x6005, 0x6105, 0x9010], |p, _| assert_eq!(p.pc(), 0x206));
+    each_profile(&[0x6005, 0x6106, 0x9010], |p, _| assert_eq!(p.pc(), 0x208));
+}
+
+#[test]
+fn skips_jump_over_xo_chip_long_loads() {
+    // SE V0, 0 followed by F000 nnnn skips all four bytes on XO-CHIP only
+    let program = [0x3000, 0xF000, 0x0300];
+    let mut p = Processor::with_program(Platform::XoChip, Platform::XoChip.quirks(), &program);
+    p.step().unwrap();
+    assert_eq!(p.pc(), 0x206);
+    let mut p = Processor::with_program(Platform::CosmacVip, Quirks::default(), &program);
+    p.step().unwrap();
+    assert_eq!(p.pc(), 0x204);
+}
+
+#[test]
+fn ld_and_add_byte() {
+    each_profile(&[0x6A12, 0x7A34], |p, _| assert_eq!(p.v()[0xA], 0x46));
+    // ADD Vx, byte wraps around and leaves VF alone
+    each_profile(&[0x6F07, 0x60FF, 0x7002], |p, _| {
+        assert_eq!(p.v()[0], 0x01);
+        assert_eq!(p.v()[0xF], 0x07);
+    });
+}
+
+#[test]
+fn ld_register() {
+    each_profile(&[0x6142, 0x8010], |p, _| assert_eq!(p.v()[0], 0x42));
+}
+
+#[test]
+fn logic_operations_and_the_vf_reset_quirk() {
+    for (op, expected) in [(0x8011, 0b1110), (0x8012, 0b1000), (0x8013, 0b0110)] {
+        each_profile(&[0x600C, 0x610A, 0x6F05, op], |p, quirks| {
+            assert_eq!(p.v()[0], expected, "{:04X}", op);
+            let vf = if quirks.logic_resets_vf { 0 } else { 5 };
+            assert_eq!(p.v()[0xF], vf, "{:04X}", op);
+        });
+    }
+}
+
+#[test]
+fn add_register_sets_carry() {
+    each_profile(&[0x60FF, 0x6102, 0x8014], |p, _| {
+        assert_eq!((p.v()[0], p.v()[0xF]), (0x01, 1));
+    });
+    each_profile(&[0x60FD, 0x6102, 0x8014], |p, _| {
+        assert_eq!((p.v()[0], p.v()[0xF]), (0xFF, 0));
+    });
+}
+
+#[test]
+fn sub_sets_not_borrow() {
+    each_profile(&[0x6005, 0x6103, 0x8015], |p, _| {
+        assert_eq!((p.v()[0], p.v()[0xF]), (2, 1));
+    });
+    each_profile(&[0x6003, 0x6105, 0x8015], |p, _| {
+        assert_eq!((p.v()[0], p.v()[0xF]), (0xFE, 0));
+    });
+    // Equal values don't borrow
+    each_profile(&[0x6005, 0x6105, 0x8015], |p, _| {
+        assert_eq!((p.v()[0], p.v()[0xF]), (0, 1));
+    });
+}
+
+#[test]
+fn subn_sets_not_borrow() {
+    each_profile(&[0x6003, 0x6105, 0x8017], |p, _| {
+        assert_eq!((p.v()[0], p.v()[0xF]), (2, 1));
+    });
+    each_profile(&[0x6005, 0x6103, 0x8017], |p, _| {
+        assert_eq!((p.v()[0], p.v()[0xF]), (0xFE, 0));
+    });
+    each_profile(&[0x6005, 0x6105, 0x8017], |p, _| {
+        assert_eq!((p.v()[0], p.v()[0xF]), (0, 1));
+    });
+}
+
+#[test]
+fn shr_and_the_shift_quirk() {
+    // V0 = 0b0110, V1 = 0b0101
+    each_profile(&[0x6006, 0x6105, 0x8016], |p, quirks| {
+        if quirks.shift_uses_vy {
+            assert_eq!((p.v()[0], p.v()[0xF]), (0b10, 1));
+        } else {
+            assert_eq!((p.v()[0], p.v()[0xF]), (0b11, 0));
+        }
+    });
+}
+
+#[test]
+fn shl_sets_vf_from_the_top_bit() {
+    // V0 = 0x41, V1 = 0x81
+    each_profile(&[0x6041, 0x6181, 0x801E], |p, quirks| {
+        if quirks.shift_uses_vy {
+            assert_eq!((p.v()[0], p.v()[0xF]), (0x02, 1));
+        } else {
+            assert_eq!((p.v()[0], p.v()[0xF]), (0x82, 0));
+        }
+    });
+    each_profile(&[0x6080, 0x8006, 0x608F, 0x800E], |p, _| {
+        assert_eq!((p.v()[0], p.v()[0xF]), (0x1E, 1));
+    });
+}
+
+#[test]
+fn flag_wins_when_vf_is_the_destination() {
+    // Each sets VF from its flag even though VF also takes the result
+    let cases = [
+        (0x8F14, 0x6FFF, 0x6102, 1), // ADD VF, V1 carries
+        (0x8F15, 0x6F01, 0x6102, 0), // SUB VF, V1 borrows
+        (0x8F17, 0x6F01, 0x6102, 1), // SUBN VF, V1 doesn't borrow
+        (0x8FF6, 0x6F03, 0x6100, 1), // SHR VF shifts out a 1
+        (0x8FFE, 0x6F80, 0x6100, 1), // SHL VF shifts out a 1
+    ];
+    for (op, load_vf, load_v1, flag) in cases {
+        each_profile(&[load_vf, load_v1, op], |p, _| {
+            assert_eq!(p.v()[0xF], flag, "{:04X}", op);
+        });
+    }
+}
+
+#[test]
+fn ld_i() {
+    each_profile(&[0xA123], |p, _| assert_eq!(p.i_register(), 0x123));
+}
+
+#[test]
+fn jp_v0_and_the_jump_quirk() {
+    // V0 = 1, V3 = 0x10, JP V0, 0x300 (or Bxnn with x = 3)
+    each_profile(&[0x6001, 0x6310, 0xB300], |p, quirks| {
+        let expected = if quirks.jump_uses_vx { 0x310 } else { 0x301 };
+        assert_eq!(p.pc(), expected);
+    });
+}
+
+#[test]
+fn rnd_is_masked_and_repeatable() {
+    let program: Vec<u16> = std::iter::repeat_n(0xC00F, 32).collect();
+    let mut values = Vec::new();
+    let mut p = Processor::with_program(Platform::CosmacVip, Quirks::default(), &program);
+    p.seed_rng(1);
+    for _ in 0..32 {
+        p.step().unwrap();
+        assert_eq!(p.v()[0] & 0xF0, 0);
+        values.push(p.v()[0]);
+    }
+    assert!(values.iter().any(|&v| v != values[0]));
+
+    let mut again = Processor::with_program(Platform::CosmacVip, Quirks::default(), &program);
+    again.seed_rng(1);
+    for value in values {
+        again.step().unwrap();
+        assert_eq!(again.v()[0], value);
+    }
+}
+
+#[test]
+fn drw_draws_and_reports_collisions() {
+    // Draw the 0 glyph at (V0, V1) = (2, 3)
+    each_profile(&[0x6002, 0x6103, 0xD015], |p, _| {
+        let pixels = lit_pixels(p);
+        assert_eq!(pixels.len(), 14);
+        assert!(pixels.contains(&(2, 3)) && pixels.contains(&(5, 7)));
+        assert_eq!(p.v()[0xF], 0);
+    });
+    // Drawing it again erases it
+    each_profile(&[0xD005, 0xD005], |p, _| {
+        assert!(lit_pixels(p).is_empty());
+        assert_eq!(p.v()[0xF], 1);
+    });
+}
+
+#[test]
+fn drw_wraps_the_start_and_clips_or_wraps_the_rest() {
+    // The 0 glyph drawn at (66, 30) starts at (2, 30) and runs off the bottom
+    each_profile(&[0x6042, 0x611E, 0xD015], |p, quirks| {
+        let pixels = lit_pixels(p);
+        assert!(pixels.contains(&(2, 30)));
+        if quirks.clip_sprites {
+            assert_eq!(pixels.len(), 6);
+        } else {
+            assert_eq!(pixels.len(), 14);
+            assert!(pixels.contains(&(2, 0)));
+        }
+    });
+}
+
+#[test]
+fn drw_with_zero_rows_draws_a_big_sprite_on_super_chip() {
+    // 32 bytes of 0xFF after the program, at 0x300
+    let mut p = Processor::with_program(
+        Platform::SuperChip,
+        Platform::SuperChip.quirks(),
+        &[0x00FF, 0xA300, 0xD000],
+    );
+    p.poke(0x300, &[0xFF; 32]).unwrap();
+    for _ in 0..3 {
+        p.step().unwrap();
+    }
+    assert_eq!(lit_pixels(&p).len(), 256);
+}
+
+#[test]
+fn drw_draws_to_each_selected_xo_chip_plane() {
+    // Select both planes, point at two rows of 0x80, draw one row
+    let mut p = Processor::with_program(
+        Platform::XoChip,
+        Platform::XoChip.quirks(),
+        &[0xF301, 0xA300, 0xD001],
+    );
+    p.poke(0x300, &[0x80, 0x80]).unwrap();
+    for _ in 0..3 {
+        p.step().unwrap();
+    }
+    assert_eq!(p.display().get(0, 0), 3);
+
+    // Clearing only the second plane leaves the first
+    let mut p = Processor::with_program(
+        Platform::XoChip,
+        Platform::XoChip.quirks(),
+        &[0xF301, 0xA300, 0xD001, 0xF201, 0x00E0],
+    );
+    p.poke(0x300, &[0x80, 0x80]).unwrap();
+    for _ in 0..5 {
+        p.step().unwrap();
+    }
+    assert_eq!(p.display().get(0, 0), 1);
+}
+
+#[test]
+fn drw_past_the_end_of_memory_fails() {
+    let mut p = Processor::with_program(Platform::CosmacVip, Quirks::default(), &[0xAFFE, 0xD005]);
+    p.step().unwrap();
+    assert!(matches!(
+        p.step(),
+        Err(Chip8Error::MemoryOutOfBounds { pc: 0x202, .. })
+    ));
+}
+
+#[test]
+fn skp_and_sknp_follow_the_keypad() {
+    for (platform, quirks) in profiles() {
+        let mut keys = [false; KEYBOARD_SIZE];
+        keys[0xA] = true;
+        for (op, pressed, expected) in [
+            (0xE09E, true, 0x206),
+            (0xE09E, false, 0x204),
+            (0xE0A1, true, 0x204),
+            (0xE0A1, false, 0x206),
+        ] {
+            let mut p = Processor::with_program(platform, quirks, &[0x600A, op]);
+            p.set_keys(if pressed {
+                keys
+            } else {
+                [false; KEYBOARD_SIZE]
+            });
+            p.step().unwrap();
+            p.step().unwrap();
+            assert_eq!(p.pc(), expected, "{:04X}", op);
+        }
+    }
+}
+
+#[test]
+fn ld_k_waits_for_a_key_to_be_released() {
+    for (platform, quirks) in profiles() {
+        let mut p = Processor::with_program(platform, quirks, &[0xF30A]);
+        p.step().unwrap();
+        p.step().unwrap();
+        assert_eq!(p.pc(), 0x200);
+        let mut keys = [false; KEYBOARD_SIZE];
+        keys[7] = true;
+        p.set_keys(keys);
+        p.step().unwrap();
+        p.step().unwrap();
+        assert_eq!(p.pc(), 0x200);
+        p.set_keys([false; KEYBOARD_SIZE]);
+        p.step().unwrap();
+        assert_eq!((p.pc(), p.v()[3]), (0x202, 7));
+    }
+}
+
+#[test]
+fn ld_k_takes_one_key_when_several_are_down() {
+    // LD V3, K; LD V4, 0x01
+    for (platform, quirks) in profiles() {
+        let mut p = Processor::with_program(platform, quirks, &[0xF30A, 0x6401]);
+        p.step().unwrap();
+        let mut keys = [false; KEYBOARD_SIZE];
+        keys[2] = true;
+        keys[9] = true;
+        keys[0xE] = true;
+        p.set_keys(keys);
+        p.step().unwrap();
+        // Letting go of the others first makes no difference
+        keys[9] = false;
+        keys[0xE] = false;
+        p.set_keys(keys);
+        p.step().unwrap();
+        assert_eq!(p.pc(), 0x200);
+        p.set_keys([false; KEYBOARD_SIZE]);
+        p.step().unwrap();
+        assert_eq!((p.pc(), p.v()[3]), (0x202, 2));
+        p.step().unwrap();
+        assert_eq!((p.pc(), p.v()[4]), (0x204, 1));
+    }
+}
+
+#[test]
+fn timers_count_down_once_per_frame() {
+    // LD V0, 3; LD DT, V0; LD ST, V0; LD V1, DT; then loop
+    let program = [0x6003, 0xF015, 0xF018, 0xF107, 0x1208];
+    for (platform, quirks) in profiles() {
+        let mut p = Processor::with_program(platform, quirks, &program);
+        p.set_cycles_per_frame(4);
+        p.run_frame().unwrap();
+        assert_eq!(p.v()[1], 3);
+        assert_eq!((p.delay_timer(), p.sound_timer()), (2, 2));
+        p.run_frame().unwrap();
+        p.run_frame().unwrap();
+        p.run_frame().unwrap();
+        assert_eq!((p.delay_timer(), p.sound_timer()), (0, 0));
+    }
+}
+
+#[test]
+fn timers_count_down_while_waiting_for_a_key() {
+    // LD V0, 3; LD DT, V0; LD ST, V0; LD V1, K
+    let program = [0x6003, 0xF015, 0xF018, 0xF10A];
+    for (platform, quirks) in profiles() {
+        let mut p = Processor::with_program(platform, quirks, &program);
+        p.set_cycles_per_frame(4);
+        p.run_frame().unwrap();
+        assert_eq!((p.delay_timer(), p.sound_timer()), (2, 2));
+        p.run_frame().unwrap();
+        p.run_frame().unwrap();
+        assert_eq!(p.pc(), 0x206);
+        assert_eq!((p.delay_timer(), p.sound_timer()), (0, 0));
+    }
+}
+
+#[test]
+fn add_i() {
+    each_profile(&[0xA100, 0x6020, 0xF01E], |p, _| {
+        assert_eq!(p.i_register(), 0x120)
+    });
+}
+
+#[test]
+fn font_addresses() {
+    each_profile(&[0x600B, 0xF029], |p, _| {
+        assert_eq!(p.i_register(), 0xB * 5);
+        assert_eq!(
+            &p.ram()[p.i_register()..p.i_register() + 5],
+            &FONT[0xB * 5..0xC * 5]
+        );
+    });
+    let p = run(
+        Platform::SuperChip,
+        Platform::SuperChip.quirks(),
+        &[0x6002, 0xF030],
+    );
+    assert_eq!(p.i_register(), BIG_FONT_ADDRESS + 20);
+    assert_eq!(p.ram()[p.i_register()], BIG_FONT[20]);
+}
+
+#[test]
+fn bcd() {
+    each_profile(&[0x60FE, 0xA300, 0xF033], |p, _| {
+        assert_eq!(&p.ram()[0x300..0x303], &[2, 5, 4]);
+        assert_eq!(p.i_register(), 0x300);
+    });
+}
+
+#[test]
+fn store_and_load_registers_and_the_memory_quirk() {
+    each_profile(&[0x6011, 0x6122, 0x6233, 0xA300, 0xF255], |p, quirks| {
+        assert_eq!(&p.ram()[0x300..0x304], &[0x11, 0x22, 0x33, 0]);
+        let expected = if quirks.load_store_increments_i {
+            0x303
+        } else {
+            0x300
+        };
+        assert_eq!(p.i_register(), expected);
+    });
+    for (platform, quirks) in profiles() {
+        let mut p = Processor::with_program(platform, quirks, &[0xA300, 0xF165]);
+        p.poke(0x300, &[0x44, 0x55, 0x66]).unwrap();
+        p.step().unwrap();
+        p.step().unwrap();
+        assert_eq!(&p.v()[..3], &[0x44, 0x55, 0]);
+        let expected = if quirks.load_store_increments_i {
+            0x302
+        } else {
+            0x300
+        };
+        assert_eq!(p.i_register(), expected);
+    }
+}
+
+#[test]
+fn store_past_the_end_of_memory_fails() {
+    let mut p = Processor::with_program(Platform::CosmacVip, Quirks::default(), &[0xAFFF, 0xF255]);
+    p.step().unwrap();
+    assert!(matches!(
+        p.step(),
+        Err(Chip8Error::MemoryOutOfBounds { .. })
+    ));
+}
+
+#[test]
+fn unknown_opcodes_fail() {
+    let mut p = Processor::with_program(Platform::CosmacVip, Quirks::default(), &[0xE0FF]);
+    assert!(matches!(
+        p.step(),
+        Err(Chip8Error::UnknownOpcode {
+            pc: 0x200,
+            opcode: 0xE0FF
+        })
+    ));
+    // XO-CHIP only instructions don't exist on the VIP
+    let mut p = Processor::with_program(Platform::CosmacVip, Quirks::default(), &[0x5012]);
+    assert!(matches!(p.step(), Err(Chip8Error::UnknownOpcode { .. })));
+}
+
+#[test]
+fn running_off_the_end_of_memory_fails() {
+    let mut p = Processor::with_program(Platform::CosmacVip, Quirks::default(), &[0x1FFF]);
+    p.step().unwrap();
+    assert!(matches!(
+        p.step(),
+        Err(Chip8Error::PcOutOfRange { pc: 0xFFF })
+    ));
+}
+
+#[test]
+fn super_chip_scrolling() {
+    let schip = (Platform::SuperChip, Platform::SuperChip.quirks());
+    // Light (8, 8) with the top row of the 0 glyph's left pixel
+    let setup = [0x6008, 0xA000, 0xD001];
+    let scrolled = |op: u16| {
+        let mut program = setup.to_vec();
+        program.push(op);
+        lit_pixels(&run(schip.0, schip.1, &program))
+    };
+    assert_eq!(scrolled(0x00C3)[0], (8, 11));
+    assert_eq!(scrolled(0x00FB)[0], (12, 8));
+    assert_eq!(scrolled(0x00FC)[0], (4, 8));
+    let xo = run(
+        Platform::XoChip,
+        Platform::XoChip.quirks(),
+        &[0x6008, 0xA000, 0xD001, 0x00D2],
+    );
+    assert_eq!(lit_pixels(&xo)[0], (8, 6));
+}
+
+#[test]
+fn super_chip_resolution_and_exit() {
+    let p = run(Platform::SuperChip, Platform::SuperChip.quirks(), &[0x00FF]);
+    assert_eq!((p.display().width(), p.display().height()), (128, 64));
+    let p = run(
+        Platform::SuperChip,
+        Platform::SuperChip.quirks(),
+        &[0x00FF, 0x00FE],
+    );
+    assert_eq!((p.display().width(), p.display().height()), (64, 32));
+    let p = run(Platform::SuperChip, Platform::SuperChip.quirks(), &[0x00FD]);
+    assert!(p.has_exited());
+    assert_eq!(p.pc(), 0x200);
+}
+
+#[test]
+fn super_chip_flag_registers() {
+    let p = run(
+        Platform::SuperChip,
+        Platform::SuperChip.quirks(),
+        &[0x6011, 0x6122, 0xF175, 0x6000, 0x6100, 0xF185],
+    );
+    assert_eq!(&p.v()[..2], &[0x11, 0x22]);
+}
+
+#[test]
+fn xo_chip_save_and_load_ranges() {
+    let xo = (Platform::XoChip, Platform::XoChip.quirks());
+    let p = run(xo.0, xo.1, &[0x6011, 0x6122, 0x6233, 0xA300, 0x5212]);
+    assert_eq!(&p.ram()[0x300..0x303], &[0x33, 0x22, 0]);
+    assert_eq!(p.i_register(), 0x300);
+
+    let mut p = Processor::with_program(xo.0, xo.1, &[0xA300, 0x5133]);
+    p.poke(0x300, &[0x44, 0x55, 0x66]).unwrap();
+    p.step().unwrap();
+    p.step().unwrap();
+    assert_eq!(&p.v()[..4], &[0, 0x44, 0x55, 0x66]);
+    assert_eq!(p.i_register(), 0x300);
+}
+
+#[test]
+fn xo_chip_long_load_planes_and_audio() {
+    let xo = (Platform::XoChip, Platform::XoChip.quirks());
+    let mut p = Processor::with_program(xo.0, xo.1, &[0xF000, 0xBEEF]);
+    p.step().unwrap();
+    assert_eq!((p.i_register(), p.pc()), (0xBEEF, 0x204));
+
+    let p = run(xo.0, xo.1, &[0xF201]);
+    assert_eq!(p.selected_planes, 2);
+
+    let mut p = Processor::with_program(xo.0, xo.1, &[0xA300, 0xF002, 0x6080, 0xF03A]);
+    p.poke(0x300, &[0xAA; AUDIO_PATTERN_SIZE]).unwrap();
+    for _ in 0..4 {
+        p.step().unwrap();
+    }
+    assert_eq!(p.audio_pattern(), Some(&[0xAA; AUDIO_PATTERN_SIZE]));
+    assert_eq!(p.pitch(), 0x80);
+}
@@ -0,0 +1,82 @@
+use chip8_emulator::audio::Sound;
+use chip8_emulator::debugger::DebugCommand;
+use chip8_emulator::frontend::Status;
+use chip8_emulator::{Chip8Error, Command, Framebuffer, Frontend, KEYBOARD_SIZE};
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+/// Wraps another frontend and adds debugger commands typed in the terminal.
+///
+/// Lines are read on a separate thread so the window keeps responding while
+/// the prompt waits.
+pub struct DebugPrompt<'a, F> {
+    frontend: &'a mut F,
+    lines: Receiver<String>,
+}
+
+impl<'a, F: Frontend> DebugPrompt<'a, F> {
+    pub fn new(frontend: &'a mut F) -> Self {
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in io::stdin().lock().lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        show_prompt();
+        DebugPrompt { frontend, lines }
+    }
+}
+
+fn show_prompt() {
+    print!("(chip8) ");
+    let _ = io::stdout().flush();
+}
+
+impl<F: Frontend> Frontend for DebugPrompt<'_, F> {
+    fn read_keyboard_inputs(&mut self) -> Option<[bool; KEYBOARD_SIZE]> {
+        self.frontend.read_keyboard_inputs()
+    }
+
+    fn take_commands(&mut self) -> Vec<Command> {
+        let mut commands = self.frontend.take_commands();
+        for line in self.lines.try_iter() {
+            if line.trim().is_empty() {
+                show_prompt();
+                continue;
+            }
+            match line.parse::<DebugCommand>() {
+                Ok(command) => commands.push(Command::Debug(command)),
+                Err(e) => {
+                    println!("{}", e);
+                    show_prompt();
+                }
+            }
+        }
+        commands
+    }
+
+    fn draw_frame(&mut self, display: &Framebuffer) {
+        self.frontend.draw_frame(display);
+    }
+
+    fn show_status(&mut self, status: &Status) {
+        self.frontend.show_status(status);
+    }
+
+    fn play_sound(&mut self, sound: Sound) {
+        self.frontend.play_sound(sound);
+    }
+
+    fn report_error(&mut self, error: &Chip8Error) {
+        self.frontend.report_error(error);
+    }
+
+    fn show_message(&mut self, message: &str) {
+        self.frontend.show_message(message);
+        show_prompt();
+    }
+}
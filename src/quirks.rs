@@ -0,0 +1,172 @@
+/*
+    Behaviours that differ between CHIP-8 interpreters.
+
+    Programs were written against whatever interpreter their author had, so the same
+    opcode can mean slightly different things depending on the ROM. The presets below
+    follow the descriptions collected by the chip8-test-suite quirks test:
+    https://github.com/Timendus/chip8-test-suite
+*/
+
+use std::fmt;
+use std::str::FromStr;
+
+/// The interpreters a ROM can have been written for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Platform {
+    CosmacVip,
+    Chip48,
+    SuperChip,
+    XoChip,
+}
+
+impl Platform {
+    pub const ALL: [Platform; 4] = [
+        Platform::CosmacVip,
+        Platform::Chip48,
+        Platform::SuperChip,
+        Platform::XoChip,
+    ];
+
+    /// The name used to select this platform on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Platform::CosmacVip => "vip",
+            Platform::Chip48 => "chip48",
+            Platform::SuperChip => "schip",
+            Platform::XoChip => "xochip",
+        }
+    }
+
+    /// Whether the SUPER-CHIP scrolling, hi-res, big font and RPL flag
+    /// instructions exist. XO-CHIP is a superset of SUPER-CHIP.
+    pub fn has_schip_instructions(self) -> bool {
+        matches!(self, Platform::SuperChip | Platform::XoChip)
+    }
+
+    /// Bytes of addressable memory. XO-CHIP extends the 4 KiB of the
+    /// original machines to the full 16-bit address space.
+    pub fn memory_size(self) -> usize {
+        match self {
+            Platform::XoChip => 65536,
+            _ => 4096,
+        }
+    }
+
+    /// The quirks a ROM written for this platform expects.
+    pub fn quirks(self) -> Quirks {
+        match self {
+            Platform::CosmacVip => Quirks {
+                shift_uses_vy: true,
+                load_store_increments_i: true,
+                jump_uses_vx: false,
+                logic_resets_vf: true,
+                clip_sprites: true,
+            },
+            Platform::Chip48 | Platform::SuperChip => Quirks {
+                shift_uses_vy: false,
+                load_store_increments_i: false,
+                jump_uses_vx: true,
+                logic_resets_vf: false,
+                clip_sprites: true,
+            },
+            Platform::XoChip => Quirks {
+                shift_uses_vy: true,
+                load_store_increments_i: true,
+                jump_uses_vx: false,
+                logic_resets_vf: false,
+                clip_sprites: false,
+            },
+        }
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Platform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "vip" | "cosmac" | "cosmac-vip" | "chip8" => Ok(Platform::CosmacVip),
+            "chip48" | "chip-48" => Ok(Platform::Chip48),
+            "schip" | "superchip" | "super-chip" => Ok(Platform::SuperChip),
+            "xochip" | "xo-chip" => Ok(Platform::XoChip),
+            _ => Err(format!(
+                "Unknown platform '{}', expected one of: vip, chip48, schip, xochip",
+                s
+            )),
+        }
+    }
+}
+
+/// Switches for each opcode whose behaviour depends on the interpreter.
+///
+/// The default is the behaviour described by Cowgod's technical reference,
+/// which is what this emulator has always implemented.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Quirks {
+    /// 8xy6/8xyE copy Vy into Vx before shifting, instead of shifting Vx in place.
+    pub shift_uses_vy: bool,
+    /// Fx55/Fx65 leave I pointing just past the last register stored or loaded.
+    pub load_store_increments_i: bool,
+    /// Bnnn jumps to xnn + Vx instead of nnn + V0.
+    pub jump_uses_vx: bool,
+    /// 8xy1/8xy2/8xy3 reset VF to 0.
+    pub logic_resets_vf: bool,
+    /// Dxyn cuts sprites off at the edge of the screen instead of wrapping them.
+    pub clip_sprites: bool,
+}
+
+impl Quirks {
+    /// The names accepted by `set` and `apply_overrides`.
+    pub const NAMES: [&'static str; 5] = ["shift", "memory", "jump", "vfreset", "clip"];
+
+    /// Turns a single quirk on or off by name.
+    pub fn set(&mut self, name: &str, value: bool) -> Result<(), String> {
+        let flag = match name {
+            "shift" => &mut self.shift_uses_vy,
+            "memory" => &mut self.load_store_increments_i,
+            "jump" => &mut self.jump_uses_vx,
+            "vfreset" => &mut self.logic_resets_vf,
+            "clip" => &mut self.clip_sprites,
+            _ => {
+                return Err(format!(
+                    "Unknown quirk '{}', expected one of: {}",
+                    name,
+                    Quirks::NAMES.join(", ")
+                ))
+            }
+        };
+        *flag = value;
+        Ok(())
+    }
+
+    /// Applies a comma separated list of overrides such as `shift=off,clip`.
+    /// A name on its own turns the quirk on.
+    pub fn apply_overrides(&mut self, overrides: &str) -> Result<(), String> {
+        for item in overrides
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+        {
+            let (name, value) = match item.split_once('=') {
+                Some((name, value)) => (name.trim(), parse_switch(value.trim())?),
+                None => (item, true),
+            };
+            self.set(name, value)?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_switch(value: &str) -> Result<bool, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "on" | "true" | "1" | "yes" => Ok(true),
+        "off" | "false" | "0" | "no" => Ok(false),
+        _ => Err(format!("Expected on or off for a quirk, got '{}'", value)),
+    }
+}
@@ -0,0 +1,177 @@
+/*
+    Rewinding.
+
+    Every frame the machine is recorded as an encoded `Snapshot`. Only the newest
+    one is kept whole; each older frame is stored as the bytes that differ from
+    the frame after it, which for most programs is a few registers and some
+    pixels rather than all of memory. Going back a frame patches the newest state
+    with the latest delta.
+
+    The deltas live in a ring buffer with a cap on the bytes it holds, so the
+    oldest frames are forgotten first once the cap is reached.
+*/
+
+use crate::snapshot::Snapshot;
+use std::collections::VecDeque;
+
+/// Memory used for rewinding unless configured otherwise: 16 MiB, typically
+/// several minutes of play.
+pub const DEFAULT_REWIND_MEMORY: usize = 16 << 20;
+
+// Delta kinds, stored in the first byte
+const PATCH: u8 = 0;
+const FULL: u8 = 1;
+// Offset and length in front of every changed run of bytes
+const RUN_HEADER_SIZE: usize = 6;
+
+pub struct Rewind {
+    latest: Option<Vec<u8>>,
+    deltas: VecDeque<Vec<u8>>,
+    used: usize,
+    limit: usize,
+}
+
+impl Rewind {
+    /// A buffer holding at most `limit` bytes of history. A limit of 0 turns
+    /// rewinding off.
+    pub fn new(limit: usize) -> Rewind {
+        Rewind {
+            latest: None,
+            deltas: VecDeque::new(),
+            used: 0,
+            limit,
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Changes the cap, dropping the oldest frames if they no longer fit.
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        self.trim();
+    }
+
+    /// Bytes currently used by the recorded history.
+    pub fn memory_used(&self) -> usize {
+        self.used
+    }
+
+    /// How many frames back it is possible to go.
+    pub fn len(&self) -> usize {
+        self.deltas.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deltas.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.latest = None;
+        self.deltas.clear();
+        self.used = 0;
+    }
+
+    /// Records the state at the end of a frame.
+    pub fn push(&mut self, snapshot: &Snapshot) {
+        if self.limit == 0 {
+            return;
+        }
+        let state = snapshot.to_bytes();
+        if let Some(previous) = self.latest.take() {
+            let delta = encode_delta(&state, &previous);
+            self.used += delta.len();
+            self.deltas.push_back(delta);
+            self.used -= previous.len();
+        }
+        self.used += state.len();
+        self.latest = Some(state);
+        self.trim();
+    }
+
+    /// Goes back up to `frames` frames and returns the state there, or `None`
+    /// if there is no earlier frame left.
+    pub fn rewind(&mut self, frames: usize) -> Option<Snapshot> {
+        let mut moved = false;
+        for _ in 0..frames {
+            let (Some(latest), Some(delta)) = (self.latest.as_mut(), self.deltas.pop_back()) else {
+                break;
+            };
+            self.used -= latest.len() + delta.len();
+            apply_delta(latest, &delta);
+            self.used += latest.len();
+            moved = true;
+        }
+        if !moved {
+            return None;
+        }
+        // Only ever holds what `push` encoded, so it always decodes
+        Snapshot::from_bytes(self.latest.as_ref()?).ok()
+    }
+
+    // Forgets the oldest frames until the history fits in the limit
+    fn trim(&mut self) {
+        while self.used > self.limit {
+            match self.deltas.pop_front() {
+                Some(delta) => self.used -= delta.len(),
+                None => {
+                    self.clear();
+                    break;
+                }
+            }
+        }
+    }
+}
+
+// Encodes how to turn `from` back into `to`
+fn encode_delta(from: &[u8], to: &[u8]) -> Vec<u8> {
+    if from.len() != to.len() {
+        let mut delta = vec![FULL];
+        delta.extend_from_slice(to);
+        return delta;
+    }
+
+    let mut delta = vec![PATCH];
+    let mut i = 0;
+    while i < to.len() {
+        if from[i] == to[i] {
+            i += 1;
+            continue;
+        }
+        // Extend the run over short stretches of equal bytes, which are cheaper
+        // to copy than to start a new run for
+        let start = i;
+        let mut end = i + 1;
+        let mut next = end;
+        while next < to.len() && next - end <= RUN_HEADER_SIZE && next - start < u16::MAX as usize {
+            if from[next] != to[next] {
+                end = next + 1;
+            }
+            next += 1;
+        }
+        delta.extend_from_slice(&(start as u32).to_le_bytes());
+        delta.extend_from_slice(&((end - start) as u16).to_le_bytes());
+        delta.extend_from_slice(&to[start..end]);
+        i = end;
+    }
+    delta
+}
+
+fn apply_delta(state: &mut Vec<u8>, delta: &[u8]) {
+    let (&kind, mut runs) = delta.split_first().unwrap();
+    if kind == FULL {
+        *state = runs.to_vec();
+        return;
+    }
+    while !runs.is_empty() {
+        let start = u32::from_le_bytes(runs[..4].try_into().unwrap()) as usize;
+        let len = u16::from_le_bytes(runs[4..6].try_into().unwrap()) as usize;
+        let bytes = &runs[RUN_HEADER_SIZE..RUN_HEADER_SIZE + len];
+        state[start..start + len].copy_from_slice(bytes);
+        runs = &runs[RUN_HEADER_SIZE + len..];
+    }
+}
+
+#[cfg(test)]
+mod tests;
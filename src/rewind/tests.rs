@@ -0,0 +1,120 @@
+// Frames from a counting program differ in a register and a byte of memory,
+// and switching to high resolution changes every pixel at once
+
+use super::*;
+use crate::{Platform, Processor};
+
+// Counts up in V0 and stores it in memory, forever
+const COUNTER: [u16; 4] = [0x7001, 0xA300, 0xF055, 0x1200];
+// The same after three additions in low resolution and a switch to high
+const SWITCHES_TO_HIRES: [u16; 8] = [
+    0x7001, 0x7001, 0x7001, 0x00FF, 0x7001, 0xA300, 0xF055, 0x1208,
+];
+
+// The state after each of the first `count` instructions of `program`
+fn states(program: &[u16], count: usize) -> Vec<Snapshot> {
+    let platform = Platform::SuperChip;
+    let mut processor = Processor::with_program(platform, platform.quirks(), program);
+    (0..count)
+        .map(|_| {
+            processor.step().unwrap();
+            processor.snapshot()
+        })
+        .collect()
+}
+
+fn history(states: &[Snapshot], limit: usize) -> Rewind {
+    let mut history = Rewind::new(limit);
+    for state in states {
+        history.push(state);
+    }
+    history
+}
+
+#[test]
+fn rewinds_to_the_exact_earlier_state() {
+    let states = states(&COUNTER, 40);
+    for frames in [1, 3, 10, 39] {
+        let mut history = history(&states, DEFAULT_REWIND_MEMORY);
+        let state = history.rewind(frames).unwrap();
+        assert_eq!(
+            state,
+            states[states.len() - 1 - frames],
+            "{} frames back",
+            frames
+        );
+        assert_eq!(history.len(), states.len() - 1 - frames);
+    }
+}
+
+#[test]
+fn rewinds_in_steps_through_every_frame() {
+    let states = states(&COUNTER, 20);
+    let mut history = history(&states, DEFAULT_REWIND_MEMORY);
+    for expected in states.iter().rev().skip(1) {
+        assert_eq!(history.rewind(1).as_ref(), Some(expected));
+    }
+    assert!(history.is_empty());
+    assert_eq!(history.rewind(1), None);
+}
+
+#[test]
+fn stops_at_the_oldest_frame() {
+    let states = states(&COUNTER, 5);
+    let mut history = history(&states, DEFAULT_REWIND_MEMORY);
+    assert_eq!(history.rewind(100).as_ref(), Some(&states[0]));
+}
+
+#[test]
+fn deltas_are_smaller_than_whole_states() {
+    let states = states(&COUNTER, 20);
+    let history = history(&states, DEFAULT_REWIND_MEMORY);
+    let whole = states[0].to_bytes().len();
+    assert!(
+        history.memory_used() < whole * 2,
+        "{} bytes",
+        history.memory_used()
+    );
+}
+
+#[test]
+fn forgets_the_oldest_frames_at_the_memory_cap() {
+    let states = states(&COUNTER, 200);
+    let whole = states[0].to_bytes().len();
+    let limit = whole + 500;
+    let mut history = history(&states, limit);
+    assert!(history.memory_used() <= limit);
+    let kept = history.len();
+    assert!(kept > 0 && kept < states.len() - 1, "{} frames kept", kept);
+
+    // The newest frames are the ones still there
+    assert_eq!(
+        history.rewind(kept).as_ref(),
+        Some(&states[states.len() - 1 - kept])
+    );
+    assert_eq!(history.rewind(1), None);
+}
+
+#[test]
+fn a_cap_below_one_state_keeps_nothing() {
+    let states = states(&COUNTER, 10);
+    let mut tiny = history(&states, 16);
+    assert_eq!(tiny.memory_used(), 0);
+    assert_eq!(tiny.rewind(1), None);
+
+    let mut off = history(&states, 0);
+    assert_eq!(off.memory_used(), 0);
+    assert_eq!(off.rewind(1), None);
+}
+
+#[test]
+fn rewinds_across_a_resolution_change() {
+    let states = states(&SWITCHES_TO_HIRES, 12);
+    assert!(!states[2].display.is_hires());
+    assert!(states[3].display.is_hires());
+
+    let mut history = history(&states, DEFAULT_REWIND_MEMORY);
+    assert_eq!(history.rewind(8).as_ref(), Some(&states[3]));
+    assert_eq!(history.rewind(1).as_ref(), Some(&states[2]));
+    assert_eq!(history.rewind(2).as_ref(), Some(&states[0]));
+}
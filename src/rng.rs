@@ -0,0 +1,53 @@
+/*
+    Random numbers for Cxkk.
+
+    The generator is SplitMix64: tiny, fast, and its entire state is a single u64,
+    so a run can be reproduced from its seed and the state can be saved and
+    restored along with the rest of the machine. The seed is kept alongside the
+    state so a run started from entropy can still be repeated.
+*/
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rng {
+    seed: u64,
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { seed, state: seed }
+    }
+
+    /// A generator started from `seed` that has since moved on to `state`.
+    pub fn resume(seed: u64, state: u64) -> Rng {
+        Rng { seed, state }
+    }
+
+    /// The seed the sequence started from.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The current state. `Rng::resume` with this value continues the
+    /// sequence from where this generator is.
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    /// A generator seeded from the operating system's entropy source.
+    pub fn from_entropy() -> Rng {
+        Rng::new(rand::random())
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    pub fn next_u8(&mut self) -> u8 {
+        (self.next_u64() >> 56) as u8
+    }
+}
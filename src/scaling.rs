@@ -0,0 +1,85 @@
+/*
+    Fitting the screen into a window.
+
+    CHIP-8 screens are tiny, so frontends scale them up to whatever size the
+    window is. `viewport` works out where the scaled screen goes, leaving
+    borders around it when its shape doesn't match the window's.
+*/
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScaleMode {
+    /// The largest whole number of window pixels per CHIP-8 pixel that fits,
+    /// so every pixel is the same size.
+    #[default]
+    Integer,
+    /// As large as fits while keeping the screen's shape.
+    Fit,
+    /// Fill the whole window, whatever its shape.
+    Stretch,
+}
+
+impl FromStr for ScaleMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "integer" => Ok(ScaleMode::Integer),
+            "fit" => Ok(ScaleMode::Fit),
+            "stretch" => Ok(ScaleMode::Stretch),
+            _ => Err(format!(
+                "Unknown scaling '{}', expected integer, fit or stretch",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ScaleMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ScaleMode::Integer => "integer",
+            ScaleMode::Fit => "fit",
+            ScaleMode::Stretch => "stretch",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The part of a window the screen is drawn to, in window pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Viewport {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Where a `screen` sized image goes in a `window`, both given as width and
+/// height. A window too small for whole number scaling falls back to `Fit`.
+pub fn viewport(mode: ScaleMode, screen: (u32, u32), window: (u32, u32)) -> Viewport {
+    let (screen_width, screen_height) = (screen.0.max(1), screen.1.max(1));
+    let (width, height) = match mode {
+        ScaleMode::Stretch => window,
+        ScaleMode::Integer if window.0 >= screen_width && window.1 >= screen_height => {
+            let scale = (window.0 / screen_width).min(window.1 / screen_height);
+            (screen_width * scale, screen_height * scale)
+        }
+        ScaleMode::Integer | ScaleMode::Fit => {
+            let scale =
+                (window.0 as f64 / screen_width as f64).min(window.1 as f64 / screen_height as f64);
+            (
+                (screen_width as f64 * scale) as u32,
+                (screen_height as f64 * scale) as u32,
+            )
+        }
+    };
+    Viewport {
+        x: (window.0 - width) / 2,
+        y: (window.1 - height) / 2,
+        width,
+        height,
+    }
+}
@@ -0,0 +1,64 @@
+/*
+    Real time pacing for the run loop.
+
+    The delay and sound timers count down at 60Hz regardless of how fast the CPU
+    runs, so time is measured in frames of 1/60th of a second. Frame deadlines are
+    computed from a fixed starting instant rather than by adding up sleeps, which
+    keeps the timers at exactly 60 ticks per second however much the sleeps jitter.
+*/
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const FRAMES_PER_SECOND: u64 = 60;
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+
+// If the loop falls further behind than this (the window was being dragged, the
+// machine was suspended, ...) stop trying to catch up and start counting afresh
+const MAX_CATCH_UP_FRAMES: u64 = 6;
+
+pub struct Scheduler {
+    start: Instant,
+    frames: u64,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scheduler {
+    pub fn new() -> Scheduler {
+        Scheduler {
+            start: Instant::now(),
+            frames: 0,
+        }
+    }
+
+    /// Sleeps until the next frame is due, then returns how many frames have
+    /// elapsed since the last call. This is normally 1, but can be more when
+    /// the caller is running behind and needs to catch up.
+    pub fn wait_for_frame(&mut self) -> u32 {
+        let next = self.deadline(self.frames + 1);
+        let now = Instant::now();
+        if next > now {
+            thread::sleep(next - now);
+        }
+
+        let elapsed = self.start.elapsed().as_nanos() as u64;
+        let due = elapsed * FRAMES_PER_SECOND / NANOS_PER_SECOND;
+        let frames = due.saturating_sub(self.frames).max(1);
+        if frames > MAX_CATCH_UP_FRAMES {
+            self.start = Instant::now();
+            self.frames = 0;
+            return 1;
+        }
+        self.frames += frames;
+        frames as u32
+    }
+
+    fn deadline(&self, frame: u64) -> Instant {
+        self.start + Duration::from_nanos(frame * NANOS_PER_SECOND / FRAMES_PER_SECOND)
+    }
+}
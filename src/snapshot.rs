@@ -0,0 +1,296 @@
+/*
+    A copy of the complete machine state.
+
+    Snapshots are what save states are made of. `Processor::snapshot` takes one and
+    `Processor::restore` puts it back. The binary encoding starts with a magic
+    number and a version so that old files can be recognised if the layout ever
+    needs to change; all numbers are little endian.
+*/
+
+use crate::error::Chip8Error;
+use crate::framebuffer::{Framebuffer, ALL_PLANES};
+use crate::processor::{AUDIO_PATTERN_SIZE, KEYBOARD_SIZE};
+use crate::quirks::{Platform, Quirks};
+use crate::rng::Rng;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 4] = b"C8SS";
+const VERSION: u16 = 2;
+// Version 1 had no RNG seed, only its state
+const VERSION_WITHOUT_SEED: u16 = 1;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    pub platform: Platform,
+    pub quirks: Quirks,
+    pub ram: Vec<u8>,
+    pub display: Framebuffer,
+    pub v: [u8; 16],
+    pub stack: [usize; 16],
+    pub sp: usize,
+    pub pc: usize,
+    pub i_register: usize,
+    pub delay_register: u8,
+    pub sound_register: u8,
+    pub keyboard_presses: [bool; KEYBOARD_SIZE],
+    pub is_waiting_for_input: bool,
+    /// The key Fx0A is waiting to be released.
+    pub pressed_key: Option<u8>,
+    pub rpl: [u8; 16],
+    pub exited: bool,
+    pub selected_planes: u8,
+    pub audio_pattern: Option<[u8; AUDIO_PATTERN_SIZE]>,
+    pub pitch: u8,
+    pub rng: Rng,
+}
+
+impl Snapshot {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.ram.len() + self.display.pixels().len() + 256);
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+
+        out.push(platform_id(self.platform));
+        out.push(quirk_bits(self.quirks));
+        out.extend_from_slice(&(self.ram.len() as u32).to_le_bytes());
+        out.extend_from_slice(&self.ram);
+        out.extend_from_slice(&(self.display.width() as u16).to_le_bytes());
+        out.extend_from_slice(&(self.display.height() as u16).to_le_bytes());
+        out.extend_from_slice(self.display.pixels());
+
+        out.extend_from_slice(&self.v);
+        for address in self.stack {
+            out.extend_from_slice(&(address as u32).to_le_bytes());
+        }
+        out.push(self.sp as u8);
+        out.extend_from_slice(&(self.pc as u32).to_le_bytes());
+        out.extend_from_slice(&(self.i_register as u32).to_le_bytes());
+        out.push(self.delay_register);
+        out.push(self.sound_register);
+        let keys = (0..KEYBOARD_SIZE)
+            .filter(|&key| self.keyboard_presses[key])
+            .fold(0u16, |bits, key| bits | 1 << key);
+        out.extend_from_slice(&keys.to_le_bytes());
+        out.push(key_wait_byte(self.is_waiting_for_input, self.pressed_key));
+
+        out.extend_from_slice(&self.rpl);
+        out.push(self.exited as u8);
+        out.push(self.selected_planes);
+        match self.audio_pattern {
+            Some(pattern) => {
+                out.push(1);
+                out.extend_from_slice(&pattern);
+            }
+            None => out.push(0),
+        }
+        out.push(self.pitch);
+        out.extend_from_slice(&self.rng.state().to_le_bytes());
+        out.extend_from_slice(&self.rng.seed().to_le_bytes());
+        out
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Snapshot, Chip8Error> {
+        let mut reader = Reader::new(data, invalid);
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(invalid("not a save state"));
+        }
+        let version = reader.u16()?;
+        if version != VERSION && version != VERSION_WITHOUT_SEED {
+            return Err(Chip8Error::InvalidSaveState(format!(
+                "unsupported version {}",
+                version
+            )));
+        }
+
+        let platform = *Platform::ALL
+            .get(reader.u8()? as usize)
+            .ok_or_else(|| invalid("unknown platform"))?;
+        let quirks = quirks_from_bits(reader.u8()?);
+        let ram_len = reader.u32()? as usize;
+        if ram_len != platform.memory_size() {
+            return Err(invalid("memory size does not match the platform"));
+        }
+        let ram = reader.take(ram_len)?.to_vec();
+        let width = reader.u16()? as usize;
+        let height = reader.u16()? as usize;
+        let display = Framebuffer::from_pixels(width, height, reader.take(width * height)?)
+            .ok_or_else(|| invalid("bad display size or pixels"))?;
+
+        let v = reader.array()?;
+        let mut stack = [0; 16];
+        for address in stack.iter_mut() {
+            *address = reader.u32()? as usize;
+        }
+        let sp = reader.u8()? as usize;
+        if sp >= stack.len() {
+            return Err(invalid("stack pointer out of range"));
+        }
+        let pc = reader.u32()? as usize;
+        let i_register = reader.u32()? as usize;
+        let delay_register = reader.u8()?;
+        let sound_register = reader.u8()?;
+        let keys = reader.u16()?;
+        let mut keyboard_presses = [false; KEYBOARD_SIZE];
+        for (key, pressed) in keyboard_presses.iter_mut().enumerate() {
+            *pressed = keys & 1 << key != 0;
+        }
+        let (is_waiting_for_input, pressed_key) = match reader.u8()? {
+            0 => (false, None),
+            1 => (true, None),
+            byte if ((byte - 2) as usize) < KEYBOARD_SIZE => (true, Some(byte - 2)),
+            _ => return Err(invalid("unknown key wait")),
+        };
+
+        let rpl = reader.array()?;
+        let exited = reader.u8()? != 0;
+        let selected_planes = reader.u8()?;
+        if selected_planes > ALL_PLANES {
+            return Err(invalid("unknown planes selected"));
+        }
+        let audio_pattern = match reader.u8()? {
+            0 => None,
+            _ => Some(reader.array()?),
+        };
+        let pitch = reader.u8()?;
+        let state = reader.u64()?;
+        let rng = match version {
+            VERSION_WITHOUT_SEED => Rng::new(state),
+            _ => Rng::resume(reader.u64()?, state),
+        };
+
+        if !reader.is_empty() {
+            return Err(invalid("unexpected data at the end"));
+        }
+
+        Ok(Snapshot {
+            platform,
+            quirks,
+            ram,
+            display,
+            v,
+            stack,
+            sp,
+            pc,
+            i_register,
+            delay_register,
+            sound_register,
+            keyboard_presses,
+            is_waiting_for_input,
+            pressed_key,
+            rpl,
+            exited,
+            selected_planes,
+            audio_pattern,
+            pitch,
+            rng,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Chip8Error> {
+        fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Snapshot, Chip8Error> {
+        Snapshot::from_bytes(&fs::read(path)?)
+    }
+}
+
+/// Where a numbered save slot for a ROM lives: next to the ROM, with the slot
+/// number in the extension, e.g. `roms/TETRIS.ss1`.
+pub fn slot_path(rom: &Path, slot: u8) -> PathBuf {
+    let mut name = rom.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".ss{}", slot));
+    rom.with_file_name(name)
+}
+
+// Whether Fx0A is waiting and for what in one byte: 0 when it isn't, 1 while it
+// waits for a key to go down and 2 plus the key while it waits for it to come
+// back up. Files from before keys were taken on release only have 0 and 1.
+fn key_wait_byte(is_waiting_for_input: bool, pressed_key: Option<u8>) -> u8 {
+    match (is_waiting_for_input, pressed_key) {
+        (false, _) => 0,
+        (true, None) => 1,
+        (true, Some(key)) => key + 2,
+    }
+}
+
+fn invalid(reason: &str) -> Chip8Error {
+    Chip8Error::InvalidSaveState(reason.to_string())
+}
+
+pub(crate) fn platform_id(platform: Platform) -> u8 {
+    Platform::ALL.iter().position(|&p| p == platform).unwrap() as u8
+}
+
+pub(crate) fn quirk_bits(quirks: Quirks) -> u8 {
+    [
+        quirks.shift_uses_vy,
+        quirks.load_store_increments_i,
+        quirks.jump_uses_vx,
+        quirks.logic_resets_vf,
+        quirks.clip_sprites,
+    ]
+    .iter()
+    .enumerate()
+    .fold(0, |bits, (i, &on)| bits | (on as u8) << i)
+}
+
+pub(crate) fn quirks_from_bits(bits: u8) -> Quirks {
+    Quirks {
+        shift_uses_vy: bits & 1 != 0,
+        load_store_increments_i: bits & 1 << 1 != 0,
+        jump_uses_vx: bits & 1 << 2 != 0,
+        logic_resets_vf: bits & 1 << 3 != 0,
+        clip_sprites: bits & 1 << 4 != 0,
+    }
+}
+
+/// Reads little endian values from a file, reporting damage with `invalid`.
+pub(crate) struct Reader<'a> {
+    data: &'a [u8],
+    invalid: fn(&str) -> Chip8Error,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(data: &'a [u8], invalid: fn(&str) -> Chip8Error) -> Reader<'a> {
+        Reader { data, invalid }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub(crate) fn take(&mut self, len: usize) -> Result<&'a [u8], Chip8Error> {
+        if len > self.data.len() {
+            return Err((self.invalid)("file is truncated"));
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    pub(crate) fn array<const N: usize>(&mut self) -> Result<[u8; N], Chip8Error> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, Chip8Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> Result<u16, Chip8Error> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, Chip8Error> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64, Chip8Error> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+}
+
+#[cfg(test)]
+mod tests;
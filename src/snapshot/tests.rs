@@ -0,0 +1,139 @@
+/*
+    The snapshots come from a machine that has run a few instructions, so the
+    encoding is checked against real state rather than defaults.
+*/
+
+use super::*;
+use crate::framebuffer::{HIRES_HEIGHT, HIRES_WIDTH};
+use crate::Processor;
+
+// An XO-CHIP machine in high resolution that has drawn on both planes and
+// used the random number generator
+fn snapshot() -> Snapshot {
+    let program: [u16; 6] = [0x00FF, 0xF301, 0x6005, 0xD015, 0xC1FF, 0xF201];
+    let mut processor =
+        Processor::with_program(Platform::XoChip, Platform::XoChip.quirks(), &program);
+    processor.seed_rng(7);
+    for _ in program {
+        processor.step().unwrap();
+    }
+    processor.snapshot()
+}
+
+// Where the display's width is in an encoded snapshot
+fn display_offset(snapshot: &Snapshot) -> usize {
+    MAGIC.len() + 2 + 1 + 1 + 4 + snapshot.ram.len()
+}
+
+fn assert_invalid(bytes: &[u8]) {
+    match Snapshot::from_bytes(bytes) {
+        Err(Chip8Error::InvalidSaveState(_)) => (),
+        other => panic!("expected an invalid save state, got {:?}", other),
+    }
+}
+
+#[test]
+fn round_trips_through_bytes() {
+    let snapshot = snapshot();
+    assert_eq!(snapshot.display.width(), HIRES_WIDTH);
+    assert_eq!(snapshot.selected_planes, 2);
+    assert!(snapshot.display.pixels().contains(&3));
+    assert_eq!(
+        Snapshot::from_bytes(&snapshot.to_bytes()).unwrap(),
+        snapshot
+    );
+}
+
+#[test]
+fn round_trips_a_key_waiting_to_be_released() {
+    let platform = Platform::CosmacVip;
+    let mut processor = Processor::with_program(platform, platform.quirks(), &[0xF20A]);
+    let mut keys = [false; KEYBOARD_SIZE];
+    keys[0xB] = true;
+    processor.set_keys(keys);
+    processor.step().unwrap();
+    processor.step().unwrap();
+    let snapshot = processor.snapshot();
+    assert_eq!(snapshot.pressed_key, Some(0xB));
+    assert_eq!(
+        Snapshot::from_bytes(&snapshot.to_bytes()).unwrap(),
+        snapshot
+    );
+
+    // The wait comes before the user flags, exit flag, planes, audio flag,
+    // pitch, state and seed
+    let mut bytes = snapshot.to_bytes();
+    let at = bytes.len() - (16 + 1 + 1 + 1 + 1 + 8 + 8) - 1;
+    assert_eq!(bytes[at], 0xB + 2);
+    bytes[at] = KEYBOARD_SIZE as u8 + 2;
+    assert_invalid(&bytes);
+}
+
+#[test]
+fn reads_version_1_files() {
+    let snapshot = snapshot();
+    // Version 1 ends with the generator's state and has no seed after it
+    let mut bytes = snapshot.to_bytes();
+    bytes[MAGIC.len()..MAGIC.len() + 2].copy_from_slice(&VERSION_WITHOUT_SEED.to_le_bytes());
+    bytes.truncate(bytes.len() - 8);
+
+    let loaded = Snapshot::from_bytes(&bytes).unwrap();
+    assert_eq!(loaded.rng.state(), snapshot.rng.state());
+    assert_eq!(
+        Snapshot {
+            rng: snapshot.rng,
+            ..loaded
+        },
+        snapshot
+    );
+}
+
+#[test]
+fn rejects_display_sizes_other_than_the_two_resolutions() {
+    let snapshot = snapshot();
+    let at = display_offset(&snapshot);
+    for (width, height) in [
+        (HIRES_WIDTH, 0),
+        (0, HIRES_HEIGHT),
+        (HIRES_WIDTH, HIRES_HEIGHT + 1),
+    ] {
+        let mut bytes = snapshot.to_bytes();
+        bytes[at..at + 2].copy_from_slice(&(width as u16).to_le_bytes());
+        bytes[at + 2..at + 4].copy_from_slice(&(height as u16).to_le_bytes());
+        assert_invalid(&bytes);
+    }
+
+    // The same number of pixels in another shape
+    let mut bytes = snapshot.to_bytes();
+    bytes[at..at + 2].copy_from_slice(&(HIRES_HEIGHT as u16).to_le_bytes());
+    bytes[at + 2..at + 4].copy_from_slice(&(HIRES_WIDTH as u16).to_le_bytes());
+    assert_invalid(&bytes);
+}
+
+#[test]
+fn rejects_pixels_and_planes_that_dont_exist() {
+    let snapshot = snapshot();
+    let mut bytes = snapshot.to_bytes();
+    bytes[display_offset(&snapshot) + 4] = ALL_PLANES + 1;
+    assert_invalid(&bytes);
+
+    // The selected planes come before the audio flag, pitch, state and seed
+    assert!(snapshot.audio_pattern.is_none());
+    let mut bytes = snapshot.to_bytes();
+    let at = bytes.len() - (1 + 1 + 8 + 8) - 1;
+    assert_eq!(bytes[at], snapshot.selected_planes);
+    bytes[at] = ALL_PLANES + 1;
+    assert_invalid(&bytes);
+}
+
+#[test]
+fn rejects_damaged_files() {
+    let bytes = snapshot().to_bytes();
+    assert_invalid(&bytes[..bytes.len() - 1]);
+    assert_invalid(&[bytes.as_slice(), &[0]].concat());
+    assert_invalid(MAGIC);
+
+    let mut bytes = bytes;
+    bytes[MAGIC.len()..MAGIC.len() + 2].copy_from_slice(&(VERSION + 1).to_le_bytes());
+    assert_invalid(&bytes);
+}
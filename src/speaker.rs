@@ -0,0 +1,47 @@
+extern crate sdl2;
+use chip8_emulator::audio::{AudioSettings, Sound, Synth};
+use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
+use sdl2::Sdl;
+
+const SAMPLE_RATE: i32 = 44100;
+
+struct SynthCallback(Synth);
+
+impl AudioCallback for SynthCallback {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        self.0.fill(out);
+    }
+}
+
+/// The SDL audio device, playing whatever the core reports each frame.
+pub struct Speaker {
+    device: AudioDevice<SynthCallback>,
+}
+
+impl Speaker {
+    pub fn new(sdl_context: &Sdl, settings: AudioSettings) -> Result<Speaker, String> {
+        let audio_subsystem = sdl_context.audio()?;
+        let desired = AudioSpecDesired {
+            freq: Some(SAMPLE_RATE),
+            channels: Some(1),
+            samples: None,
+        };
+        let device = audio_subsystem.open_playback(None, &desired, |spec| {
+            SynthCallback(Synth::new(settings, spec.freq as u32))
+        })?;
+        device.resume();
+        Ok(Speaker { device })
+    }
+
+    pub fn set_sound(&mut self, sound: Sound) {
+        self.device.lock().0.set_sound(sound);
+    }
+
+    pub fn toggle_mute(&mut self) {
+        let mut callback = self.device.lock();
+        let settings = callback.0.settings_mut();
+        settings.muted = !settings.muted;
+    }
+}
@@ -0,0 +1,334 @@
+use chip8_emulator::debugger::DebugCommand;
+use chip8_emulator::filter::{FilterStage, FlickerFilter};
+use chip8_emulator::frontend::Status;
+use chip8_emulator::keymap::Keymap;
+use chip8_emulator::palette::Palettes;
+use chip8_emulator::textmode::{self, Glyphs};
+use chip8_emulator::{Command, Framebuffer, Frontend, KEYBOARD_SIZE};
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::style::Print;
+use crossterm::terminal::{
+    self, Clear, ClearType, DisableLineWrap, EnableLineWrap, EnterAlternateScreen,
+    LeaveAlternateScreen,
+};
+use crossterm::{execute, queue};
+use std::io::{self, Stdout, Write};
+use std::time::{Duration, Instant};
+
+// Most terminals only send a key when it goes down, then again as it repeats,
+// so a key counts as held until this long after it last came in
+const KEY_HOLD: Duration = Duration::from_millis(250);
+// How long a message stays on the status line
+const MESSAGE_TIME: Duration = Duration::from_secs(3);
+// How often the frame rate on the status line is worked out
+const FPS_INTERVAL: Duration = Duration::from_secs(1);
+// Frames stepped back per frame when rewinding with shift held
+const FAST_REWIND_FRAMES: u32 = 4;
+
+/// How the terminal draws the screen.
+pub struct TerminalSettings {
+    pub glyphs: Glyphs,
+    pub filter: FlickerFilter,
+}
+
+/// Plays in the terminal, for machines with no display such as over SSH.
+///
+/// The terminal is switched to raw mode and its alternate screen for as long
+/// as this lives, and put back the way it was when it's dropped.
+pub struct TerminalScreen {
+    stdout: Stdout,
+    glyphs: Glyphs,
+    filter: FilterStage,
+    palettes: Palettes,
+    // Host key names in capitals, and the CHIP-8 keys they press
+    keys: Vec<(String, usize)>,
+    // When each CHIP-8 key last came in, while it's down
+    held: [Option<Instant>; KEYBOARD_SIZE],
+    // When backspace last came in, and how fast to rewind
+    rewind: Option<(Instant, u32)>,
+    // Whether the terminal says when keys are let go, so a key can stay held
+    // for as long as it really is
+    releases: bool,
+    commands: Vec<Command>,
+    status: Option<Status>,
+    message: Option<(String, Instant)>,
+    // Frames drawn since the frame rate was last worked out
+    frames: u32,
+    counted_since: Instant,
+    fps: f64,
+    // The lines on the terminal, so only the ones that change are redrawn
+    shown: Vec<String>,
+}
+
+impl TerminalScreen {
+    pub fn new(
+        settings: TerminalSettings,
+        palettes: Palettes,
+        keymap: &Keymap,
+    ) -> Result<TerminalScreen, String> {
+        terminal::enable_raw_mode().map_err(|e| format!("Unable to use the terminal: {}", e))?;
+        // Dropping this puts the terminal back if anything below fails
+        let mut screen = TerminalScreen {
+            stdout: io::stdout(),
+            glyphs: settings.glyphs,
+            filter: FilterStage::new(settings.filter),
+            palettes,
+            keys: keymap
+                .bindings()
+                .map(|(name, key)| (name.to_uppercase(), key))
+                .collect(),
+            held: [None; KEYBOARD_SIZE],
+            rewind: None,
+            releases: false,
+            commands: Vec::new(),
+            status: None,
+            message: None,
+            frames: 0,
+            counted_since: Instant::now(),
+            fps: 0.0,
+            shown: Vec::new(),
+        };
+        execute!(
+            screen.stdout,
+            EnterAlternateScreen,
+            Hide,
+            DisableLineWrap,
+            Clear(ClearType::All)
+        )
+        .map_err(|e| format!("Unable to use the terminal: {}", e))?;
+        if terminal::supports_keyboard_enhancement().unwrap_or(false) {
+            screen.releases = execute!(
+                screen.stdout,
+                PushKeyboardEnhancementFlags(
+                    KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+                        | KeyboardEnhancementFlags::REPORT_EVENT_TYPES
+                )
+            )
+            .is_ok();
+        }
+        Ok(screen)
+    }
+
+    // Takes in a key going down, repeating or coming up. Returns false if it
+    // asks to quit.
+    fn handle_key(&mut self, key: KeyEvent, now: Instant) -> bool {
+        let down = key.kind != KeyEventKind::Release;
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+        // Raw mode turns off ctrl-c, so it's handled here like escape
+        let quit = key.code == KeyCode::Esc || (control && key.code == KeyCode::Char('c'));
+        if down && quit {
+            return false;
+        }
+
+        if let Some(name) = key_name(key.code) {
+            for (bound, key) in &self.keys {
+                if *bound == name {
+                    self.held[*key] = down.then_some(now);
+                }
+            }
+        }
+        // Holding backspace runs time backwards, faster with shift
+        if key.code == KeyCode::Backspace {
+            let frames = if shift { FAST_REWIND_FRAMES } else { 1 };
+            self.rewind = down.then_some((now, frames));
+        }
+        if !down {
+            return true;
+        }
+
+        // Repeats can only be told apart where the terminal reports them. These
+        // keys are in `keymap::RESERVED_KEYS`, so none of them is also bound.
+        let first = key.kind == KeyEventKind::Press;
+        match key.code {
+            // T switches to the next palette
+            KeyCode::Char('t' | 'T') if first => {
+                self.palettes.next();
+                let message = format!("Palette: {}", self.palettes.name());
+                self.show_message(&message);
+            }
+            // - and = change how many instructions run per frame
+            KeyCode::Char('-') => self.commands.push(Command::SlowDown),
+            KeyCode::Char('=' | '+') => self.commands.push(Command::SpeedUp),
+            // P pauses and continues, I steps one instruction and N steps
+            // over subroutine calls
+            KeyCode::Char('p' | 'P') if first => self
+                .commands
+                .push(Command::Debug(DebugCommand::TogglePause)),
+            KeyCode::Char('i' | 'I') => self.commands.push(Command::Debug(DebugCommand::Step)),
+            KeyCode::Char('n' | 'N') => self.commands.push(Command::Debug(DebugCommand::StepOver)),
+            // F1-F9 save to that slot, holding shift loads from it instead
+            KeyCode::F(slot @ 1..=9) if first => {
+                if shift {
+                    self.commands.push(Command::LoadState(slot));
+                } else {
+                    self.commands.push(Command::SaveState(slot));
+                }
+            }
+            _ => (),
+        }
+        true
+    }
+
+    // Whether a key that last came in at `since` is still down
+    fn is_held(&self, since: Option<Instant>, now: Instant) -> bool {
+        since.is_some_and(|since| self.releases || now.duration_since(since) < KEY_HOLD)
+    }
+
+    fn draw(&mut self, display: &Framebuffer) -> io::Result<()> {
+        let palette = self.palettes.current();
+        let colours = self.filter.apply(display, &palette);
+        let mut lines = textmode::render(colours, display.width(), palette.colour(0), self.glyphs);
+        // Cut the screen short rather than lose the status line off the bottom
+        let (_, rows) = terminal::size()?;
+        lines.truncate((rows as usize).saturating_sub(1));
+        lines.push(self.status_line());
+
+        // A new resolution or terminal size leaves nothing worth keeping
+        if lines.len() != self.shown.len() {
+            queue!(self.stdout, Clear(ClearType::All))?;
+            self.shown.clear();
+        }
+        for (row, line) in lines.iter().enumerate() {
+            if self.shown.get(row) != Some(line) {
+                queue!(
+                    self.stdout,
+                    MoveTo(0, row as u16),
+                    Print(line),
+                    Clear(ClearType::UntilNewLine)
+                )?;
+            }
+        }
+        self.stdout.flush()?;
+        self.shown = lines;
+        Ok(())
+    }
+
+    fn status_line(&mut self) -> String {
+        let mut line = match &self.status {
+            Some(status) => format!(
+                " PC {:#05X}  DT {:3}  ST {:3}  {} ipf  {:.0} fps",
+                status.pc,
+                status.delay_timer,
+                status.sound_timer,
+                status.cycles_per_frame,
+                self.fps
+            ),
+            None => format!(" {:.0} fps", self.fps),
+        };
+        if self.status.is_some_and(|status| status.paused) {
+            line += "  paused";
+        }
+        if let Some((message, at)) = &self.message {
+            if at.elapsed() < MESSAGE_TIME {
+                line += &format!("  {}", message);
+            } else {
+                self.message = None;
+            }
+        }
+        // Reverse video sets it apart from the screen
+        format!("\x1b[7m{} \x1b[0m", line)
+    }
+
+    fn count_frame(&mut self) {
+        self.frames += 1;
+        let elapsed = self.counted_since.elapsed();
+        if elapsed >= FPS_INTERVAL {
+            self.fps = self.frames as f64 / elapsed.as_secs_f64();
+            self.frames = 0;
+            self.counted_since = Instant::now();
+        }
+    }
+}
+
+impl Frontend for TerminalScreen {
+    fn read_keyboard_inputs(&mut self) -> Option<[bool; KEYBOARD_SIZE]> {
+        let now = Instant::now();
+        loop {
+            match event::poll(Duration::ZERO) {
+                Ok(true) => (),
+                Ok(false) => break,
+                // Nothing more will come in, so there is no way to play on
+                Err(_) => return None,
+            }
+            match event::read() {
+                Ok(Event::Key(key)) => {
+                    if !self.handle_key(key, now) {
+                        return None;
+                    }
+                }
+                Ok(Event::Resize(..)) => self.shown.clear(),
+                Ok(_) => (),
+                Err(_) => return None,
+            }
+        }
+
+        if let Some((since, frames)) = self.rewind {
+            if self.is_held(Some(since), now) {
+                self.commands.push(Command::Rewind(frames));
+            }
+        }
+        let mut keys = [false; KEYBOARD_SIZE];
+        for (key, since) in keys.iter_mut().zip(self.held) {
+            *key = self.is_held(since, now);
+        }
+        Some(keys)
+    }
+
+    fn take_commands(&mut self) -> Vec<Command> {
+        std::mem::take(&mut self.commands)
+    }
+
+    fn show_status(&mut self, status: &Status) {
+        self.status = Some(*status);
+    }
+
+    fn draw_frame(&mut self, display: &Framebuffer) {
+        self.count_frame();
+        // There's nowhere to say it didn't work, the terminal is the screen
+        let _ = self.draw(display);
+    }
+
+    // Printing would scroll the screen, so messages go on the status line
+    fn show_message(&mut self, message: &str) {
+        self.message = Some((message.to_string(), Instant::now()));
+    }
+}
+
+impl Drop for TerminalScreen {
+    fn drop(&mut self) {
+        if self.releases {
+            let _ = execute!(self.stdout, PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(self.stdout, EnableLineWrap, Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+// The keymap's name for a key, in capitals, for the keys terminals send.
+// Terminals send characters rather than where keys are on the keyboard, so
+// letters and digits go by what's printed on them.
+fn key_name(code: KeyCode) -> Option<String> {
+    let name = match code {
+        KeyCode::Char(' ') => "SPACE",
+        KeyCode::Char(c) => return Some(c.to_uppercase().to_string()),
+        KeyCode::Up => "UP",
+        KeyCode::Down => "DOWN",
+        KeyCode::Left => "LEFT",
+        KeyCode::Right => "RIGHT",
+        KeyCode::Enter => "RETURN",
+        KeyCode::Tab => "TAB",
+        KeyCode::Home => "HOME",
+        KeyCode::End => "END",
+        KeyCode::PageUp => "PAGEUP",
+        KeyCode::PageDown => "PAGEDOWN",
+        KeyCode::Insert => "INSERT",
+        KeyCode::Delete => "DELETE",
+        _ => return None,
+    };
+    Some(name.to_string())
+}
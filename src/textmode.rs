@@ -0,0 +1,167 @@
+/*
+    Drawing the screen with text.
+
+    Terminals can't show pixels, but a character cell can stand in for a few
+    of them. Each cell is drawn in one of two ways:
+
+    half blocks - `▀` with its top half in one pixel's colour and its bottom
+        half in the next row's, so a cell is 1x2 pixels and keeps every colour
+    braille - the eight dots of a braille pattern, so a cell is 2x4 pixels,
+        with the dots that aren't background sharing one colour
+
+    Colours are 24-bit ANSI escapes, which nearly every terminal emulator
+    understands.
+*/
+
+use crate::palette::Rgb;
+use std::fmt::{self, Write};
+use std::str::FromStr;
+
+// The bit for each dot of a braille pattern, by row and then column
+const BRAILLE_DOTS: [[u8; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+const BRAILLE_BLANK: u32 = 0x2800;
+const RESET: &str = "\x1b[0m";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Glyphs {
+    /// Two pixels to a cell, one above the other.
+    #[default]
+    HalfBlocks,
+    /// Eight pixels to a cell, two across and four down.
+    Braille,
+}
+
+impl Glyphs {
+    /// Pixels covered by a cell, across and down.
+    pub fn cell_size(self) -> (usize, usize) {
+        match self {
+            Glyphs::HalfBlocks => (1, 2),
+            Glyphs::Braille => (2, 4),
+        }
+    }
+
+    /// Columns and rows of cells needed for a `width` by `height` screen.
+    pub fn cells(self, width: usize, height: usize) -> (usize, usize) {
+        let (across, down) = self.cell_size();
+        (width.div_ceil(across), height.div_ceil(down))
+    }
+}
+
+impl FromStr for Glyphs {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "blocks" => Ok(Glyphs::HalfBlocks),
+            "braille" => Ok(Glyphs::Braille),
+            _ => Err(format!(
+                "Unknown glyphs '{}', expected blocks or braille",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Glyphs {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Glyphs::HalfBlocks => "blocks",
+            Glyphs::Braille => "braille",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Lines of coloured text showing a screen of `colours`, given row by row
+/// and `width` pixels across. Braille leaves the dots that are `background`
+/// out. Each line ends by resetting the colours.
+pub fn render(colours: &[Rgb], width: usize, background: Rgb, glyphs: Glyphs) -> Vec<String> {
+    let height = colours.len().checked_div(width).unwrap_or(0);
+    let (columns, rows) = glyphs.cells(width, height);
+    // Pixels past the edge of a screen that doesn't fill its last cells
+    let pixel = |x: usize, y: usize| {
+        if x < width && y < height {
+            colours[y * width + x]
+        } else {
+            background
+        }
+    };
+
+    (0..rows)
+        .map(|row| {
+            let mut line = Line::default();
+            for column in 0..columns {
+                match glyphs {
+                    Glyphs::HalfBlocks => {
+                        let top = pixel(column, row * 2);
+                        let bottom = pixel(column, row * 2 + 1);
+                        if top == bottom {
+                            line.cell(None, bottom, ' ');
+                        } else {
+                            line.cell(Some(top), bottom, '▀');
+                        }
+                    }
+                    Glyphs::Braille => {
+                        let mut pattern = 0;
+                        let mut lit = Vec::new();
+                        for (dy, bits) in BRAILLE_DOTS.iter().enumerate() {
+                            for (dx, &bit) in bits.iter().enumerate() {
+                                let colour = pixel(column * 2 + dx, row * 4 + dy);
+                                if colour != background {
+                                    pattern |= bit;
+                                    lit.push(colour);
+                                }
+                            }
+                        }
+                        if lit.is_empty() {
+                            line.cell(None, background, ' ');
+                        } else {
+                            let glyph = char::from_u32(BRAILLE_BLANK + pattern as u32)
+                                .expect("braille patterns are characters");
+                            line.cell(Some(average(&lit)), background, glyph);
+                        }
+                    }
+                }
+            }
+            line.text.push_str(RESET);
+            line.text
+        })
+        .collect()
+}
+
+// A line being built, remembering the colours last set so that runs of
+// cells in the same colours don't repeat them
+#[derive(Default)]
+struct Line {
+    text: String,
+    foreground: Option<Rgb>,
+    background: Option<Rgb>,
+}
+
+impl Line {
+    // Adds a character, with no foreground colour for a blank cell
+    fn cell(&mut self, foreground: Option<Rgb>, background: Rgb, glyph: char) {
+        if let Some(Rgb(r, g, b)) = foreground.filter(|&colour| self.foreground != Some(colour)) {
+            let _ = write!(self.text, "\x1b[38;2;{};{};{}m", r, g, b);
+            self.foreground = foreground;
+        }
+        if self.background != Some(background) {
+            let Rgb(r, g, b) = background;
+            let _ = write!(self.text, "\x1b[48;2;{};{};{}m", r, g, b);
+            self.background = Some(background);
+        }
+        self.text.push(glyph);
+    }
+}
+
+fn average(colours: &[Rgb]) -> Rgb {
+    let count = colours.len() as u32;
+    let sum = colours.iter().fold([0; 3], |[r, g, b], &Rgb(cr, cg, cb)| {
+        [r + cr as u32, g + cg as u32, b + cb as u32]
+    });
+    Rgb(
+        (sum[0] / count) as u8,
+        (sum[1] / count) as u8,
+        (sum[2] / count) as u8,
+    )
+}
@@ -0,0 +1,151 @@
+/*
+    Golden-frame tests for the bundled ROMs.
+
+    Each ROM runs headlessly with a fixed seed and a scripted set of key
+    presses, and the screen at a few checkpoints is compared with the frames
+    recorded in tests/golden/<ROM>.txt. After a change that is meant to alter
+    what the ROMs draw, regenerate the recordings with
+
+        UPDATE_GOLDEN=1 cargo test --test golden
+
+    and review the differences before committing them.
+*/
+
+use chip8_emulator::headless::KeyScript;
+use chip8_emulator::Processor;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SEED: u64 = 0xC8;
+// Frames at which the screen is recorded, the last one ending the run
+const CHECKPOINTS: [u64; 3] = [120, 300, 600];
+// Presses a spread of keys, enough to start most games and move things around
+const DEFAULT_KEYS: &str = "
+    60:5 64:-
+    120:4 150:- 180:6 210:-
+    260:2 264:- 300:8 304:-
+    360:1 364:- 400:c 404:-
+    450:5 454:- 500:6 540:-
+";
+
+macro_rules! golden_tests {
+    ($($name:ident: $rom:literal,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                check($rom, keys_for($rom));
+            }
+        )*
+    };
+}
+
+golden_tests! {
+    puzzle15: "15PUZZLE",
+    airplane: "AIRPLANE",
+    blinky: "BLINKY",
+    blitz: "BLITZ",
+    bowling: "BOWLING",
+    brix: "BRIX",
+    cave: "CAVE",
+    connect4: "CONNECT4",
+    guess: "GUESS",
+    hidden: "HIDDEN",
+    ibm: "IBM",
+    invaders: "INVADERS",
+    kaleid: "KALEID",
+    maze: "MAZE",
+    merlin: "MERLIN",
+    missile: "MISSILE",
+    pong: "PONG",
+    pong2: "PONG2",
+    puzzle: "PUZZLE",
+    syzygy: "SYZYGY",
+    tank: "TANK",
+    tetris: "TETRIS",
+    tictac: "TICTAC",
+    ufo: "UFO",
+    vbrix: "VBRIX",
+    vers: "VERS",
+    wipeoff: "WIPEOFF",
+}
+
+// Games whose controls the default script doesn't reach
+fn keys_for(rom: &str) -> &'static str {
+    match rom {
+        // Serve, then move the left paddle up and down
+        "PONG" | "PONG2" => "60:1 90:- 200:4 260:- 400:1 430:-",
+        // Start, then move left and right while firing
+        "INVADERS" => "60:5 64:- 120:4 160:- 200:5 204:- 260:6 320:- 400:5 404:-",
+        // Rotate, move and drop pieces
+        "TETRIS" => "60:4 64:- 100:5 130:- 200:6 230:- 300:7 360:- 400:4 404:-",
+        _ => DEFAULT_KEYS,
+    }
+}
+
+fn check(rom: &str, keys: &str) {
+    let actual = record(rom, keys);
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(format!("{}.txt", rom));
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, &actual).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&path).unwrap_or_else(|e| {
+        panic!(
+            "Unable to read {}: {}. Run with UPDATE_GOLDEN=1 to record it.",
+            path.display(),
+            e
+        )
+    });
+    if actual != expected {
+        let actual_path = failure_path(rom);
+        fs::write(&actual_path, &actual).unwrap();
+        let line = expected
+            .lines()
+            .zip(actual.lines())
+            .position(|(expected, actual)| expected != actual)
+            .unwrap_or(expected.lines().count().min(actual.lines().count()));
+        panic!(
+            "{} no longer matches {} from line {}. The new frames are in {}.",
+            rom,
+            path.display(),
+            line + 1,
+            actual_path.display()
+        );
+    }
+}
+
+// Runs the ROM and describes the screen at each checkpoint
+fn record(rom: &str, keys: &str) -> String {
+    let keys: KeyScript = keys.parse().unwrap();
+    let mut processor = Processor::new();
+    processor.seed_rng(SEED);
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("roms").join(rom);
+    processor.load_rom(&path.to_string_lossy()).unwrap();
+
+    let mut frames = String::new();
+    let mut frame = 0;
+    for checkpoint in CHECKPOINTS {
+        while frame < checkpoint && !processor.has_exited() {
+            processor.set_keys(keys.keys_at(frame));
+            processor
+                .run_frame()
+                .unwrap_or_else(|e| panic!("{} failed on frame {}: {}", rom, frame, e));
+            frame += 1;
+        }
+        frames += &format!("frame {}\n{}", checkpoint, processor.display().to_text());
+    }
+    frames
+}
+
+// Where a mismatching recording is written for comparison
+fn failure_path(rom: &str) -> PathBuf {
+    let directory = Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden");
+    fs::create_dir_all(&directory).unwrap();
+    directory.join(format!("{}.txt", rom))
+}